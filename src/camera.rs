@@ -0,0 +1,217 @@
+use cgmath::{Deg, EuclideanSpace, InnerSpace, Matrix4, Point3, Rad, Vector3};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    Perspective { fovy: Rad<f32>, near: f32, far: f32 },
+    //Height of the view volume in world units, the width follows from the aspect ratio
+    Orthographic { height: f32, near: f32, far: f32 },
+}
+
+impl Projection {
+    pub fn matrix(&self, aspect: f32) -> Matrix4<f32> {
+        //A zero sized window (e.g. while minimized) would otherwise produce NaNs
+        let aspect = if aspect.is_finite() && aspect > 0.0 { aspect } else { 1.0 };
+
+        let gl = match *self {
+            Projection::Perspective { fovy, near, far } => cgmath::perspective(fovy, aspect, near, far),
+            Projection::Orthographic { height, near, far } => {
+                let half_h = height / 2.0;
+                let half_w = half_h * aspect;
+                cgmath::ortho(-half_w, half_w, -half_h, half_h, near, far)
+            }
+        };
+
+        vulkan_clip() * gl
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Camera {
+    pub eye: Point3<f32>,
+    pub target: Point3<f32>,
+    pub up: Vector3<f32>,
+    pub projection: Projection,
+}
+
+impl Camera {
+    pub fn new(eye: Point3<f32>, target: Point3<f32>, projection: Projection) -> Self {
+        Camera {
+            eye,
+            target,
+            up: Vector3::unit_y(),
+            projection,
+        }
+    }
+
+    pub fn perspective(eye: Point3<f32>, target: Point3<f32>) -> Self {
+        Camera::new(eye, target, Projection::Perspective {
+            fovy: Deg(45.0).into(),
+            near: 0.1,
+            far: 1000.0,
+        })
+    }
+
+    pub fn orthographic(eye: Point3<f32>, target: Point3<f32>, height: f32) -> Self {
+        Camera::new(eye, target, Projection::Orthographic {
+            height,
+            near: 0.1,
+            far: 1000.0,
+        })
+    }
+
+    pub fn view(&self) -> Matrix4<f32> {
+        Matrix4::look_at(self.eye, self.target, self.up)
+    }
+
+    pub fn projection(&self, aspect: f32) -> Matrix4<f32> {
+        self.projection.matrix(aspect)
+    }
+
+    pub fn view_projection(&self, aspect: f32) -> Matrix4<f32> {
+        self.projection(aspect) * self.view()
+    }
+}
+
+//Axis aligned bounding box, used to frame a model with the camera
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bounds {
+    pub min: Point3<f32>,
+    pub max: Point3<f32>,
+}
+
+impl Bounds {
+    pub fn from_points<I: IntoIterator<Item = Point3<f32>>>(points: I) -> Option<Self> {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+        Some(points.fold(Bounds { min: first, max: first }, |b, p| Bounds {
+            min: Point3::new(b.min.x.min(p.x), b.min.y.min(p.y), b.min.z.min(p.z)),
+            max: Point3::new(b.max.x.max(p.x), b.max.y.max(p.y), b.max.z.max(p.z)),
+        }))
+    }
+
+    pub fn center(&self) -> Point3<f32> {
+        self.min.midpoint(self.max)
+    }
+
+    //Radius of the bounding sphere around center()
+    pub fn radius(&self) -> f32 {
+        (self.max - self.min).magnitude() / 2.0
+    }
+}
+
+//cgmath builds OpenGL style clip space (y up, depth in -1..1), Vulkan expects y down and depth in 0..1
+#[cfg_attr(rustfmt, rustfmt_skip)]
+pub fn vulkan_clip() -> Matrix4<f32> {
+    Matrix4::new(
+        1.0,  0.0, 0.0, 0.0,
+        0.0, -1.0, 0.0, 0.0,
+        0.0,  0.0, 0.5, 0.0,
+        0.0,  0.0, 0.5, 1.0,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::Vector4;
+
+    const EPSILON: f32 = 1e-4;
+
+    //Normalized device coordinates of a view space point
+    fn ndc(matrix: Matrix4<f32>, x: f32, y: f32, z: f32) -> Vector3<f32> {
+        let clip = matrix * Vector4::new(x, y, z, 1.0);
+        clip.truncate() / clip.w
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < EPSILON, "{} is not {}", actual, expected);
+    }
+
+    #[test]
+    fn vulkan_clip_maps_gl_depth_to_zero_one() {
+        assert_close(ndc(vulkan_clip(), 0.0, 0.0, -1.0).z, 0.0);
+        assert_close(ndc(vulkan_clip(), 0.0, 0.0, 0.0).z, 0.5);
+        assert_close(ndc(vulkan_clip(), 0.0, 0.0, 1.0).z, 1.0);
+    }
+
+    #[test]
+    fn vulkan_clip_flips_y_only() {
+        let point = ndc(vulkan_clip(), 0.25, 0.75, 0.0);
+        assert_close(point.x, 0.25);
+        assert_close(point.y, -0.75);
+    }
+
+    #[test]
+    fn perspective_puts_near_and_far_at_zero_and_one() {
+        let projection = Projection::Perspective { fovy: Deg(60.0).into(), near: 0.5, far: 200.0 };
+        let matrix = projection.matrix(16.0 / 9.0);
+        assert_close(ndc(matrix, 0.0, 0.0, -0.5).z, 0.0);
+        assert_close(ndc(matrix, 0.0, 0.0, -200.0).z, 1.0);
+        assert_close(ndc(matrix, 3.0, -2.0, -0.5).z, 0.0);
+    }
+
+    #[test]
+    fn orthographic_puts_near_and_far_at_zero_and_one() {
+        let projection = Projection::Orthographic { height: 10.0, near: 1.0, far: 50.0 };
+        let matrix = projection.matrix(2.0);
+        assert_close(ndc(matrix, 0.0, 0.0, -1.0).z, 0.0);
+        assert_close(ndc(matrix, 0.0, 0.0, -50.0).z, 1.0);
+        //The top right corner of the view volume, with y pointing down in Vulkan
+        let corner = ndc(matrix, 10.0, 5.0, -20.0);
+        assert_close(corner.x, 1.0);
+        assert_close(corner.y, -1.0);
+    }
+
+    #[test]
+    fn perspective_camera_shows_points_above_the_target_at_the_top() {
+        let camera = Camera::perspective(Point3::new(0.0, 0.0, 10.0), Point3::new(0.0, 0.0, 0.0));
+        let clip = camera.view_projection(1.0) * Vector4::new(0.0, 1.0, 0.0, 1.0);
+        assert!(clip.y / clip.w < 0.0);
+        let depth = clip.z / clip.w;
+        assert!(0.0 < depth && depth < 1.0);
+    }
+
+    #[test]
+    fn invalid_aspect_falls_back_to_one() {
+        let projections = [
+            Projection::Perspective { fovy: Deg(45.0).into(), near: 0.1, far: 100.0 },
+            Projection::Orthographic { height: 4.0, near: 0.1, far: 100.0 },
+        ];
+        for projection in projections.iter() {
+            let square = projection.matrix(1.0);
+            assert_eq!(projection.matrix(0.0), square);
+            assert_eq!(projection.matrix(-2.0), square);
+            assert_eq!(projection.matrix(std::f32::NAN), square);
+            assert_eq!(projection.matrix(std::f32::INFINITY), square);
+            assert_ne!(projection.matrix(2.0), square);
+        }
+    }
+
+    #[test]
+    fn bounds_of_points() {
+        let bounds = Bounds::from_points(vec![
+            Point3::new(1.0, -2.0, 3.0),
+            Point3::new(-1.0, 4.0, 0.0),
+            Point3::new(0.0, 0.0, -3.0),
+        ]).unwrap();
+        assert_eq!(bounds.min, Point3::new(-1.0, -2.0, -3.0));
+        assert_eq!(bounds.max, Point3::new(1.0, 4.0, 3.0));
+        assert_eq!(bounds.center(), Point3::new(0.0, 1.0, 0.0));
+        //Half the diagonal of a 2 x 6 x 6 box
+        assert_close(bounds.radius(), 76f32.sqrt() / 2.0);
+    }
+
+    #[test]
+    fn bounds_of_a_single_point() {
+        let point = Point3::new(2.0, 3.0, 4.0);
+        let bounds = Bounds::from_points(Some(point)).unwrap();
+        assert_eq!(bounds, Bounds { min: point, max: point });
+        assert_eq!(bounds.center(), point);
+        assert_eq!(bounds.radius(), 0.0);
+    }
+
+    #[test]
+    fn no_bounds_without_points() {
+        assert_eq!(Bounds::from_points(Vec::new()), None);
+    }
+}
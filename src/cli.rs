@@ -0,0 +1,140 @@
+use renderervk::gpu::GpuChoice;
+use renderervk::present::PresentPolicy;
+use renderervk::texture;
+use renderervk::RendererOptions;
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Usage: renderervk [OPTIONS] [MODEL.obj]
+
+Renders MODEL.obj, or the built-in teapot when no model is given.
+
+Options:
+    --texture IMAGE    Image used as the diffuse texture of the model
+    --lights FILE      Lights to draw the model with instead of the default one, see src/light.rs
+    --filter MODE      Texture filtering, nearest or linear (default)
+    --anisotropy N     Anisotropic filtering samples, 1 to turn it off (default 16)
+    --address MODE     Texture wrapping, repeat (default), mirror, clamp or border
+    --present MODE     Presentation, vsync (default), mailbox or immediate. V cycles through them
+    --frames N         Frames the CPU may record ahead of the GPU (default 2)
+    --watch-shaders    Recompile the shaders when src/*.glsl change (needs the hot-reload feature)
+    --headless OUT.png Render a single frame to OUT.png without opening a window
+    --size WxH         Size of the headless frame (default 800x600)
+    --golden DIR       Render the reference scenes headless and compare them to the images in DIR
+    --bless            With --golden, overwrite the golden images with the new renders
+    --tolerance N      With --golden, how far a channel may be off before a pixel fails (default 2)
+    --gpu DEVICE       Device index or part of its name, overriding the automatic choice
+    --list-gpus        Print the available devices and their properties
+    -h, --help         Print this message
+
+The device can also be chosen with the RENDERERVK_GPU environment variable, in the same format as --gpu.";
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Options {
+    pub model: Option<PathBuf>,
+    pub texture: Option<PathBuf>,
+    pub lights: Option<PathBuf>,
+    pub renderer: RendererOptions,
+    pub headless: Option<PathBuf>,
+    pub size: Option<[u32; 2]>,
+    pub golden: Option<PathBuf>,
+    pub bless: bool,
+    pub tolerance: Option<u8>,
+    pub list_gpus: bool,
+    pub help: bool,
+}
+
+impl Options {
+    //Expects the arguments without the program name, i.e. env::args().skip(1)
+    pub fn from_args<I: IntoIterator<Item = String>>(args: I) -> Result<Options, String> {
+        let mut options = Options::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-h" | "--help" => options.help = true,
+                "--texture" => {
+                    let path = args.next().ok_or("--texture expects an image path")?;
+                    options.texture = Some(PathBuf::from(path));
+                },
+                "--lights" => {
+                    let path = args.next().ok_or("--lights expects a file path")?;
+                    options.lights = Some(PathBuf::from(path));
+                },
+                "--filter" => {
+                    let mode = args.next().ok_or("--filter expects nearest or linear")?;
+                    options.renderer.sampler.filter = texture::parse_filter(&mode)
+                        .ok_or_else(|| format!("Unknown filter {}, expected nearest or linear", mode))?;
+                },
+                "--anisotropy" => {
+                    let samples = args.next().ok_or("--anisotropy expects a number")?;
+                    options.renderer.sampler.anisotropy = samples.parse::<f32>().ok().filter(|&n| n >= 1.0)
+                        .ok_or_else(|| format!("Invalid anisotropy {}, expected a number of at least 1", samples))?;
+                },
+                "--address" => {
+                    let mode = args.next().ok_or("--address expects repeat, mirror, clamp or border")?;
+                    options.renderer.sampler.address_mode = texture::parse_address_mode(&mode)
+                        .ok_or_else(|| format!("Unknown address mode {}, expected repeat, mirror, clamp or border", mode))?;
+                },
+                "--present" => {
+                    let mode = args.next().ok_or("--present expects vsync, mailbox or immediate")?;
+                    options.renderer.present = PresentPolicy::parse(&mode)
+                        .ok_or_else(|| format!("Unknown present mode {}, expected vsync, mailbox or immediate", mode))?;
+                },
+                "--frames" => {
+                    let frames = args.next().ok_or("--frames expects a number")?;
+                    options.renderer.frames_in_flight = frames.parse::<usize>().ok().filter(|&n| n >= 1)
+                        .ok_or_else(|| format!("Invalid frame count {}, expected a number of at least 1", frames))?;
+                },
+                "--watch-shaders" => options.renderer.watch_shaders = true,
+                "--headless" => {
+                    let path = args.next().ok_or("--headless expects an output path")?;
+                    options.headless = Some(PathBuf::from(path));
+                },
+                "--size" => {
+                    let size = args.next().ok_or("--size expects WIDTHxHEIGHT")?;
+                    options.size = Some(parse_size(&size)
+                        .ok_or_else(|| format!("Invalid size {}, expected WIDTHxHEIGHT like 800x600", size))?);
+                },
+                "--golden" => {
+                    let path = args.next().ok_or("--golden expects a directory")?;
+                    options.golden = Some(PathBuf::from(path));
+                },
+                "--bless" => options.bless = true,
+                "--tolerance" => {
+                    let tolerance = args.next().ok_or("--tolerance expects a number")?;
+                    options.tolerance = Some(tolerance.parse::<u8>()
+                        .map_err(|_| format!("Invalid tolerance {}, expected a number from 0 to 255", tolerance))?);
+                },
+                "--gpu" => {
+                    let choice = args.next().ok_or("--gpu expects a device index or name")?;
+                    options.renderer.gpu = Some(GpuChoice::parse(&choice));
+                },
+                "--list-gpus" => options.list_gpus = true,
+                flag if flag.starts_with('-') => return Err(format!("Unknown option {}", flag)),
+                _ => {
+                    if options.model.is_some() {
+                        return Err(format!("Unexpected argument {}, only one model can be given", arg));
+                    }
+                    options.model = Some(PathBuf::from(arg));
+                }
+            }
+        }
+
+        if (options.bless || options.tolerance.is_some()) && options.golden.is_none() {
+            return Err("--bless and --tolerance only apply to --golden".to_string());
+        }
+
+        Ok(options)
+    }
+}
+
+fn parse_size(size: &str) -> Option<[u32; 2]> {
+    let mut parts = size.splitn(2, 'x');
+    let width = parts.next()?.parse::<u32>().ok()?;
+    let height = parts.next()?.parse::<u32>().ok()?;
+    if width == 0 || height == 0 {
+        return None;
+    }
+    Some([width, height])
+}
@@ -0,0 +1,144 @@
+use std::error::Error;
+use std::fmt;
+use vulkano::OomError;
+use vulkano::buffer::cpu_access::WriteLockError;
+use vulkano::command_buffer::{AutoCommandBufferBuilderContextError, BeginRenderPassError, BuildError,
+    CommandBufferExecError, DrawIndexedError};
+use vulkano::descriptor::descriptor_set::{PersistentDescriptorSetBuildError, PersistentDescriptorSetError};
+use vulkano::device::DeviceCreationError;
+use vulkano::framebuffer::{FramebufferCreationError, RenderPassCreationError};
+use vulkano::image::ImageCreationError;
+use vulkano::instance::InstanceCreationError;
+use vulkano::memory::DeviceMemoryAllocError;
+use vulkano::pipeline::GraphicsPipelineCreationError;
+use vulkano::sampler::SamplerCreationError;
+use vulkano::swapchain::{AcquireError, CapabilitiesError, SwapchainCreationError};
+use vulkano::sync::FlushError;
+
+#[derive(Debug)]
+pub enum RendererError {
+    Instance(InstanceCreationError),
+    //Opening the window or creating its surface
+    Surface(vulkano_win::CreationError),
+    //Why no device could be used, see gpu::choose
+    NoDevice(String),
+    NoQueue,
+    Device(DeviceCreationError),
+    SurfaceCapabilities(CapabilitiesError),
+    Swapchain(SwapchainCreationError),
+    Acquire(AcquireError),
+    RenderPass(RenderPassCreationError),
+    //The render pass the pipelines are built for has no subpass to draw in
+    NoSubpass,
+    Framebuffer(FramebufferCreationError),
+    Pipeline(GraphicsPipelineCreationError),
+    ShaderLoad(OomError),
+    Image(ImageCreationError),
+    Sampler(SamplerCreationError),
+    Buffer(DeviceMemoryAllocError),
+    //Recording or submitting the commands of a frame
+    Frame(Box<Error>),
+    //Recording or submitting the commands that copy a texture and its mip levels to the device
+    Upload(Box<Error>),
+}
+
+impl fmt::Display for RendererError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RendererError::Instance(err) => write!(f, "could not create the Vulkan instance: {}", err),
+            RendererError::Surface(err) => write!(f, "could not create the window: {}", err),
+            RendererError::NoDevice(reason) => write!(f, "{}", reason),
+            RendererError::NoQueue => write!(f, "the device has no queue that can draw"),
+            RendererError::Device(err) => write!(f, "could not create the device: {}", err),
+            RendererError::SurfaceCapabilities(err) => write!(f, "could not query the surface: {}", err),
+            RendererError::Swapchain(err) => write!(f, "could not create the swapchain: {}", err),
+            RendererError::Acquire(err) => write!(f, "could not acquire a swapchain image: {}", err),
+            RendererError::RenderPass(err) => write!(f, "could not create the render pass: {}", err),
+            RendererError::NoSubpass => write!(f, "the render pass has no subpass"),
+            RendererError::Framebuffer(err) => write!(f, "could not create a framebuffer: {}", err),
+            RendererError::Pipeline(err) => write!(f, "could not create a graphics pipeline: {}", err),
+            RendererError::ShaderLoad(err) => write!(f, "could not load the shaders: {}", err),
+            RendererError::Image(err) => write!(f, "could not create an image: {}", err),
+            RendererError::Sampler(err) => write!(f, "could not create the texture sampler: {}", err),
+            RendererError::Buffer(err) => write!(f, "could not allocate a buffer: {}", err),
+            RendererError::Frame(err) => write!(f, "could not draw the frame: {}", err),
+            RendererError::Upload(err) => write!(f, "could not upload a texture: {}", err),
+        }
+    }
+}
+
+impl Error for RendererError {
+    fn source(&self) -> Option<&(Error + 'static)> {
+        match self {
+            RendererError::Instance(err) => Some(err),
+            RendererError::Surface(err) => Some(err),
+            RendererError::NoDevice(_) | RendererError::NoQueue | RendererError::NoSubpass => None,
+            RendererError::Device(err) => Some(err),
+            RendererError::SurfaceCapabilities(err) => Some(err),
+            RendererError::Swapchain(err) => Some(err),
+            RendererError::Acquire(err) => Some(err),
+            RendererError::RenderPass(err) => Some(err),
+            RendererError::Framebuffer(err) => Some(err),
+            RendererError::Pipeline(err) => Some(err),
+            RendererError::ShaderLoad(err) => Some(err),
+            RendererError::Image(err) => Some(err),
+            RendererError::Sampler(err) => Some(err),
+            RendererError::Buffer(err) => Some(err),
+            RendererError::Frame(err) => Some(err.as_ref()),
+            RendererError::Upload(err) => Some(err.as_ref()),
+        }
+    }
+}
+
+macro_rules! from_error {
+    ($($error:ty => $variant:ident),* $(,)*) => {
+        $(
+            impl From<$error> for RendererError {
+                fn from(err: $error) -> Self {
+                    RendererError::$variant(err)
+                }
+            }
+        )*
+    };
+}
+
+from_error! {
+    InstanceCreationError => Instance,
+    vulkano_win::CreationError => Surface,
+    DeviceCreationError => Device,
+    CapabilitiesError => SurfaceCapabilities,
+    SwapchainCreationError => Swapchain,
+    AcquireError => Acquire,
+    RenderPassCreationError => RenderPass,
+    FramebufferCreationError => Framebuffer,
+    GraphicsPipelineCreationError => Pipeline,
+    ImageCreationError => Image,
+    SamplerCreationError => Sampler,
+    DeviceMemoryAllocError => Buffer,
+}
+
+//OomError is left out on purpose, it means a failed shader load or a failed frame depending on where
+//it comes from
+macro_rules! from_frame_error {
+    ($($error:ty),* $(,)*) => {
+        $(
+            impl From<$error> for RendererError {
+                fn from(err: $error) -> Self {
+                    RendererError::Frame(Box::new(err))
+                }
+            }
+        )*
+    };
+}
+
+from_frame_error! {
+    PersistentDescriptorSetError,
+    PersistentDescriptorSetBuildError,
+    BeginRenderPassError,
+    AutoCommandBufferBuilderContextError,
+    DrawIndexedError,
+    BuildError,
+    CommandBufferExecError,
+    WriteLockError,
+    FlushError,
+}
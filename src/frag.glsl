@@ -1,22 +1,103 @@
 #version 450
 
 layout(location = 0) out vec4 f_color;
-//layout(location = 0) in vec2 tex_coords;
 layout(location = 0) in vec3 v_normal;
+layout(location = 1) in vec2 v_tex_coord;
+layout(location = 2) in vec3 v_position;
+layout(location = 3) in vec3 v_eye;
 
-//layout(set = 0, binding = 0) uniform ColorData {
-//   vec3 color_data; 
-//} current_color; 
+layout(set = 1, binding = 0) uniform Material {
+    //rgb is Kd, a is d
+    vec4 diffuse;
+    //rgb is Ks, a is Ns
+    vec4 specular;
+} material;
 
-//layout(set = 1, binding = 0) uniform sampler2D tex;
+layout(set = 1, binding = 1) uniform sampler2D diffuse_map;
+layout(set = 1, binding = 2) uniform sampler2D bump_map;
 
-const vec3 LIGHT = vec3(0.0,0.0,1.0);
+//Must match MAX_LIGHTS and the kinds in light.rs
+const int MAX_LIGHTS = 8;
+const int DIRECTIONAL = 0;
+const int POINT = 1;
+const int SPOT = 2;
+
+struct Light {
+    //xyz in world space, w is the kind
+    vec4 position;
+    //xyz points away from the light
+    vec4 direction;
+    //rgb is the color times the intensity
+    vec4 color;
+    //Constant, linear and quadratic falloff with distance
+    vec4 attenuation;
+    //Cosines of the inner and outer spot angles
+    vec4 cone;
+};
+
+layout(set = 0, binding = 1) uniform Lights {
+    vec4 ambient;
+    Light lights[MAX_LIGHTS];
+    uint count;
+} lighting;
+
+//Tilts the normal along the screen space slope of the bump map, which needs no tangents (Mikkelsen 2010)
+vec3 bumped_normal(vec3 normal) {
+    vec3 dp_dx = dFdx(v_position);
+    vec3 dp_dy = dFdy(v_position);
+    float height = texture(bump_map, v_tex_coord).r;
+    float dh_dx = dFdx(height);
+    float dh_dy = dFdy(height);
+
+    vec3 r1 = cross(dp_dy, normal);
+    vec3 r2 = cross(normal, dp_dx);
+    float det = dot(dp_dx, r1);
+    if (abs(det) < 1e-12) {
+        return normal;
+    }
+    vec3 gradient = sign(det) * (dh_dx * r1 + dh_dy * r2);
+    return normalize(abs(det) * normal - gradient);
+}
+
+//Blinn-Phong, the diffuse and specular light one light adds to the surface
+vec3 shade(Light light, vec3 base, vec3 normal, vec3 view) {
+    int kind = int(light.position.w);
+    vec3 to_light = -normalize(light.direction.xyz);
+    float falloff = 1.0;
+    if (kind != DIRECTIONAL) {
+        vec3 offset = light.position.xyz - v_position;
+        float distance = length(offset);
+        to_light = offset / max(distance, 1e-6);
+        falloff = 1.0 / max(dot(light.attenuation.xyz, vec3(1.0, distance, distance * distance)), 1e-6);
+    }
+    if (kind == SPOT) {
+        float angle = dot(-to_light, normalize(light.direction.xyz));
+        falloff *= smoothstep(light.cone.y, light.cone.x, angle);
+    }
+
+    float diffuse = max(dot(normal, to_light), 0.0);
+    if (diffuse == 0.0) {
+        return vec3(0.0);
+    }
+    vec3 half_dir = normalize(to_light + view);
+    float specular = pow(max(dot(normal, half_dir), 0.0), max(material.specular.a, 1.0));
+    return (base * diffuse + material.specular.rgb * specular) * light.color.rgb * falloff;
+}
 
 void main() {
-    //f_color = texture(tex, tex_coords);
-    //vec4(current_color.color_data, 1.0); 
-    float brightness = dot(normalize(v_normal), normalize(LIGHT));
-    vec3 dark_color = vec3(0.6, 0.0, 0.0);
-    vec3 light_color = vec3(1.0, 0.0, 0.0);
-    f_color = vec4(mix(dark_color, light_color, brightness), 1.0);
+    vec4 base = material.diffuse * texture(diffuse_map, v_tex_coord);
+
+    //Lines and points usually come without normals, draw them fully lit
+    if (length(v_normal) == 0.0) {
+        f_color = base;
+        return;
+    }
+
+    vec3 normal = bumped_normal(normalize(v_normal));
+    vec3 view = normalize(v_eye - v_position);
+    vec3 color = lighting.ambient.rgb * base.rgb;
+    for (uint i = 0; i < min(lighting.count, uint(MAX_LIGHTS)); i++) {
+        color += shade(lighting.lights[i], base.rgb, normal, view);
+    }
+    f_color = vec4(color, base.a);
 }
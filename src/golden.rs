@@ -0,0 +1,250 @@
+use super::gpu::GpuChoice;
+use super::headless;
+use super::light::{Attenuation, Light, Lighting};
+use super::mesh::{Mesh, Model};
+use super::mtl::Material;
+use super::texture::SamplerOptions;
+use super::validation::ValidationOptions;
+use super::{Indices, ModelVertex};
+use image::{Rgba, RgbaImage};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+//Small enough to keep the checked in images light, big enough to show shading changes
+const SIZE: [u32; 2] = [256, 256];
+//Drivers round and interpolate a little differently, so channels may be off by this much by default
+pub const DEFAULT_TOLERANCE: u8 = 2;
+
+pub struct Scene {
+    pub name: &'static str,
+    pub model: Model,
+    pub fallback_material: Material,
+}
+
+pub fn scenes() -> Vec<Scene> {
+    vec![
+        Scene {
+            name: "teapot",
+            model: Model::teapot(),
+            fallback_material: Material::default(),
+        },
+        Scene {
+            name: "textured_quad",
+            model: Model { meshes: vec![quad()], materials: Vec::new(), lighting: Lighting::default() },
+            fallback_material: Material {
+                diffuse: [1.0, 1.0, 1.0],
+                diffuse_map: Some(PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/src/res/texture.png"))),
+                ..Material::default()
+            },
+        },
+        Scene {
+            name: "lights",
+            model: Model { lighting: three_lights(), ..Model::teapot() },
+            fallback_material: Material {
+                diffuse: [0.8, 0.8, 0.8],
+                specular: [0.5, 0.5, 0.5],
+                shininess: 32.0,
+                ..Material::default()
+            },
+        },
+    ]
+}
+
+//One light of every kind in a different color, so each one's contribution can be told apart. The
+//teapot is about 180 units wide and the camera looks at it from +z.
+fn three_lights() -> Lighting {
+    Lighting {
+        ambient: [0.05, 0.05, 0.05],
+        lights: vec![
+            Light::Directional { direction: [1.0, -1.0, -0.5], color: [0.3, 0.4, 1.0], intensity: 0.6 },
+            Light::Point {
+                position: [150.0, 50.0, 100.0],
+                color: [1.0, 0.6, 0.3],
+                intensity: 1.5,
+                attenuation: Attenuation { constant: 1.0, linear: 0.0, quadratic: 0.00002 },
+            },
+            Light::Spot {
+                position: [0.0, 250.0, 50.0],
+                direction: [0.0, -1.0, -0.2],
+                color: [0.4, 1.0, 0.4],
+                intensity: 1.0,
+                attenuation: Attenuation::default(),
+                inner_angle: 10.0,
+                outer_angle: 20.0,
+            },
+        ],
+    }
+}
+
+//Unit quad facing +z, which the default orbit camera looks at
+fn quad() -> Mesh {
+    let corners = [((-1.0, -1.0), (0.0, 1.0)), ((1.0, -1.0), (1.0, 1.0)), ((1.0, 1.0), (1.0, 0.0)), ((-1.0, 1.0), (0.0, 0.0))];
+    Mesh {
+        name: "quad".to_string(),
+        material: None,
+        vertices: corners.iter().map(|&((x, y), tex_coord)| ModelVertex {
+            position: (x, y, 0.0),
+            normal: (0.0, 0.0, 1.0),
+            tex_coord,
+        }).collect(),
+        indices: Indices::U16(vec![0, 1, 2, 0, 2, 3]),
+        lines: Indices::U16(Vec::new()),
+        points: Indices::U16(Vec::new()),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Comparison {
+    //Pixels with a channel further than the tolerance from the golden image
+    pub mismatched: usize,
+    pub max_difference: u8,
+}
+
+pub fn compare(actual: &RgbaImage, expected: &RgbaImage, tolerance: u8) -> Result<Comparison, String> {
+    if actual.dimensions() != expected.dimensions() {
+        return Err(format!("size is {:?}, expected {:?}", actual.dimensions(), expected.dimensions()));
+    }
+
+    let mut comparison = Comparison { mismatched: 0, max_difference: 0 };
+    for (a, e) in actual.pixels().zip(expected.pixels()) {
+        let difference = pixel_difference(a, e);
+        comparison.max_difference = comparison.max_difference.max(difference);
+        if difference > tolerance {
+            comparison.mismatched += 1;
+        }
+    }
+    Ok(comparison)
+}
+
+//Mismatched pixels in red over a faded copy of the golden image
+pub fn diff_image(actual: &RgbaImage, expected: &RgbaImage, tolerance: u8) -> RgbaImage {
+    RgbaImage::from_fn(expected.width(), expected.height(), |x, y| {
+        let e = expected.get_pixel(x, y);
+        let mismatched = x >= actual.width() || y >= actual.height()
+            || pixel_difference(actual.get_pixel(x, y), e) > tolerance;
+        if mismatched {
+            Rgba([255, 0, 0, 255])
+        } else {
+            let grey = ((e.data[0] as u32 + e.data[1] as u32 + e.data[2] as u32) / 3 / 4 + 191) as u8;
+            Rgba([grey, grey, grey, 255])
+        }
+    })
+}
+
+fn pixel_difference(a: &Rgba<u8>, b: &Rgba<u8>) -> u8 {
+    a.data.iter().zip(b.data.iter()).map(|(&a, &b)| (a as i16 - b as i16).abs() as u8).max().unwrap_or(0)
+}
+
+//Renders every scene and compares it to `<name>.png` in the directory. Failures leave `<name>.actual.png`
+//and `<name>.diff.png` next to it. Blessing writes the render as the new golden, without it a missing
+//golden image is a failure. Returns whether every scene matched.
+pub fn run(directory: &Path, bless: bool, tolerance: u8, gpu: Option<&GpuChoice>, validation: &ValidationOptions)
+    -> Result<bool, Box<Error>> {
+
+    let sampler = SamplerOptions::default();
+    let (queues, validation) = headless::init_headless(gpu, validation, &sampler)?;
+    let mut passed = true;
+
+    for scene in scenes() {
+        let actual = headless::render(&queues, &scene.model, &scene.fallback_material, &sampler, SIZE)?;
+        validation.check();
+        let golden = directory.join(format!("{}.png", scene.name));
+
+        if bless {
+            actual.save(&golden)?;
+            println!("{}: wrote {}", scene.name, golden.display());
+            continue;
+        }
+        if !golden.exists() {
+            passed = false;
+            let actual_path = directory.join(format!("{}.actual.png", scene.name));
+            actual.save(&actual_path)?;
+            println!("{}: FAILED, there is no {}, review {} and run with --bless to accept it", scene.name,
+                golden.display(), actual_path.display());
+            continue;
+        }
+
+        let expected = image::open(&golden)?.to_rgba();
+        let failure = match compare(&actual, &expected, tolerance) {
+            Ok(Comparison { mismatched: 0, .. }) => None,
+            Ok(comparison) => Some(format!("{} pixels differ, by up to {}", comparison.mismatched,
+                comparison.max_difference)),
+            Err(message) => Some(message),
+        };
+
+        match failure {
+            None => println!("{}: ok", scene.name),
+            Some(message) => {
+                passed = false;
+                let diff = directory.join(format!("{}.diff.png", scene.name));
+                actual.save(directory.join(format!("{}.actual.png", scene.name)))?;
+                diff_image(&actual, &expected, tolerance).save(&diff)?;
+                println!("{}: FAILED, {}, see {}", scene.name, message, diff.display());
+            }
+        }
+    }
+
+    Ok(passed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filled(width: u32, height: u32, pixel: [u8; 4]) -> RgbaImage {
+        RgbaImage::from_pixel(width, height, Rgba(pixel))
+    }
+
+    #[test]
+    fn identical_images_match() {
+        let image = filled(4, 4, [10, 20, 30, 255]);
+        assert_eq!(compare(&image, &image, 0), Ok(Comparison { mismatched: 0, max_difference: 0 }));
+    }
+
+    #[test]
+    fn differences_within_the_tolerance_pass() {
+        let expected = filled(4, 4, [100, 100, 100, 255]);
+        let mut actual = expected.clone();
+        actual.put_pixel(1, 2, Rgba([102, 99, 100, 255]));
+        assert_eq!(compare(&actual, &expected, 2), Ok(Comparison { mismatched: 0, max_difference: 2 }));
+    }
+
+    #[test]
+    fn differences_beyond_the_tolerance_are_counted() {
+        let expected = filled(4, 4, [100, 100, 100, 255]);
+        let mut actual = expected.clone();
+        actual.put_pixel(0, 0, Rgba([103, 100, 100, 255]));
+        actual.put_pixel(3, 3, Rgba([100, 100, 100, 0]));
+        assert_eq!(compare(&actual, &expected, 2), Ok(Comparison { mismatched: 2, max_difference: 255 }));
+    }
+
+    #[test]
+    fn different_sizes_are_an_error() {
+        assert!(compare(&filled(4, 4, [0; 4]), &filled(4, 3, [0; 4]), 2).is_err());
+    }
+
+    #[test]
+    fn diff_image_marks_mismatched_pixels_red() {
+        let expected = filled(2, 2, [0, 0, 0, 255]);
+        let mut actual = expected.clone();
+        actual.put_pixel(1, 0, Rgba([255, 255, 255, 255]));
+        actual.put_pixel(0, 1, Rgba([1, 0, 0, 255]));
+
+        let diff = diff_image(&actual, &expected, 2);
+        assert_eq!(diff.dimensions(), (2, 2));
+        assert_eq!(diff.get_pixel(1, 0), &Rgba([255, 0, 0, 255]));
+        //Matching pixels show the golden image faded towards white
+        assert_eq!(diff.get_pixel(0, 0), &Rgba([191, 191, 191, 255]));
+        assert_eq!(diff.get_pixel(0, 1), &Rgba([191, 191, 191, 255]));
+    }
+
+    #[test]
+    fn diff_image_marks_pixels_outside_a_smaller_render() {
+        let expected = filled(3, 1, [255, 255, 255, 255]);
+        let actual = filled(2, 1, [255, 255, 255, 255]);
+
+        let diff = diff_image(&actual, &expected, 0);
+        assert_eq!(diff.get_pixel(0, 0), &Rgba([254, 254, 254, 255]));
+        assert_eq!(diff.get_pixel(2, 0), &Rgba([255, 0, 0, 255]));
+    }
+}
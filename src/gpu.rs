@@ -0,0 +1,298 @@
+use std::env;
+use std::sync::Arc;
+use vulkano::device::{DeviceExtensions, Features, Queue, QueuesIter};
+use vulkano::instance::{Instance, InstanceCreationError, InstanceExtensions, PhysicalDevice,
+    PhysicalDeviceType, QueueFamily};
+use vulkano::swapchain::Surface;
+use winit::Window;
+use super::texture::SamplerOptions;
+
+//Same format as --gpu, used when no device was chosen explicitly
+pub const GPU_ENV_VAR: &str = "RENDERERVK_GPU";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum GpuChoice {
+    //As listed by --list-gpus
+    Index(usize),
+    //Case insensitive part of the device name
+    Name(String),
+}
+
+impl GpuChoice {
+    pub fn parse(choice: &str) -> GpuChoice {
+        match choice.parse::<usize>() {
+            Ok(index) => GpuChoice::Index(index),
+            Err(_) => GpuChoice::Name(choice.to_lowercase()),
+        }
+    }
+
+    pub fn from_env() -> Option<GpuChoice> {
+        env::var(GPU_ENV_VAR).ok().filter(|choice| !choice.is_empty()).map(|choice| GpuChoice::parse(&choice))
+    }
+
+    fn matches(&self, physical_device: PhysicalDevice) -> bool {
+        match self {
+            GpuChoice::Index(index) => physical_device.index() == *index,
+            GpuChoice::Name(name) => physical_device.name().to_lowercase().contains(name.as_str()),
+        }
+    }
+}
+
+//Only these are enabled on the device. Points are drawn 3 pixels wide, lines keep the default width.
+pub fn required_features(sampler: &SamplerOptions) -> Features {
+    Features {
+        sampler_anisotropy: sampler.anisotropy > 1.0,
+        large_points: true,
+        ..Features::none()
+    }
+}
+
+//The names of the required features that aren't supported, covering what required_features can ask for
+fn missing_features(supported: &Features, required: &Features) -> Vec<&'static str> {
+    let features = [
+        (required.sampler_anisotropy, supported.sampler_anisotropy, "sampler_anisotropy"),
+        (required.large_points, supported.large_points, "large_points"),
+        (required.wide_lines, supported.wide_lines, "wide_lines"),
+    ];
+    features.iter().filter(|&&(required, supported, _)| required && !supported).map(|&(_, _, name)| name).collect()
+}
+
+//The swapchain is only needed when drawing to a window
+pub fn required_extensions(surface: Option<&Surface<Window>>) -> DeviceExtensions {
+    DeviceExtensions {
+        khr_swapchain: surface.is_some(),
+        ..DeviceExtensions::none()
+    }
+}
+
+//A graphics queue family that can also present to the surface, if there is one
+pub fn queue_family<'a>(physical_device: PhysicalDevice<'a>, surface: Option<&Surface<Window>>) -> Option<QueueFamily<'a>> {
+    physical_device.queue_families().find(|&q| {
+        q.supports_graphics() && surface.map_or(true, |surface| surface.is_supported(q).unwrap_or(false))
+    })
+}
+
+//The queues work is submitted to. On devices with a single queue family the transfer and compute queues
+//are the graphics queue itself.
+#[derive(Clone)]
+pub struct Queues {
+    pub graphics: Arc<Queue>,
+    pub transfer: Arc<Queue>,
+    pub compute: Arc<Queue>,
+}
+
+impl Queues {
+    pub fn single(queue: Arc<Queue>) -> Queues {
+        Queues { graphics: queue.clone(), transfer: queue.clone(), compute: queue }
+    }
+
+    //Work on a separate transfer queue has to signal a semaphore before the graphics queue can use it
+    pub fn separate_transfer(&self) -> bool {
+        !self.transfer.is_same(&self.graphics)
+    }
+}
+
+//The queue families to create queues from, in the order Device::new returns them. No family appears twice:
+//vulkano creates buffers and images shared concurrently between Device::active_queue_families, which has an
+//entry per queue, and Vulkan doesn't allow a family to be listed twice there.
+pub struct QueueRequests<'a> {
+    pub families: Vec<(QueueFamily<'a>, f32)>,
+    transfer: Option<usize>,
+    compute: Option<usize>,
+}
+
+impl<'a> QueueRequests<'a> {
+    //Transfer and compute queues come from families other than the graphics one. Transfer only families,
+    //the copy engines of discrete GPUs, are preferred for uploads. When the transfer and compute queues
+    //come from the same family they share a single queue.
+    pub fn new(physical_device: PhysicalDevice<'a>, graphics: QueueFamily<'a>) -> QueueRequests<'a> {
+        let mut families = vec![(graphics, 1.0)];
+
+        let transfer_family = physical_device.queue_families()
+            .filter(|q| q.id() != graphics.id() && (q.supports_transfers() || q.supports_compute()))
+            .min_by_key(|q| (q.supports_graphics(), q.supports_compute()));
+        let transfer = transfer_family.map(|family| {
+            families.push((family, 0.5));
+            families.len() - 1
+        });
+
+        let compute_family = physical_device.queue_families()
+            .filter(|q| q.id() != graphics.id() && q.supports_compute() && !q.supports_graphics())
+            .max_by_key(|q| q.queues_count());
+        let compute = compute_family.map(|family| match transfer {
+            Some(transfer) if families[transfer].0.id() == family.id() => transfer,
+            _ => {
+                families.push((family, 0.5));
+                families.len() - 1
+            }
+        });
+
+        QueueRequests { families, transfer, compute }
+    }
+
+    pub fn queues(&self, queues: QueuesIter) -> Option<Queues> {
+        let queues = queues.collect::<Vec<_>>();
+        let graphics = queues.get(0)?.clone();
+        let queue = |index: Option<usize>| index.and_then(|i| queues.get(i)).cloned().unwrap_or_else(|| graphics.clone());
+        Some(Queues { transfer: queue(self.transfer), compute: queue(self.compute), graphics })
+    }
+}
+
+//Why the device can't be used, None when it can
+pub fn unsuitable(physical_device: PhysicalDevice, surface: Option<&Surface<Window>>, features: &Features)
+    -> Option<String> {
+
+    let missing = missing_features(physical_device.supported_features(), features);
+    if !missing.is_empty() {
+        return Some(format!("missing required features {}", missing.join(", ")));
+    }
+    let required = required_extensions(surface);
+    if DeviceExtensions::supported_by_device(physical_device).intersection(&required) != required {
+        return Some("missing required extensions".to_string());
+    }
+    if queue_family(physical_device, surface).is_none() {
+        return Some(match surface {
+            Some(_) => "no queue that can draw to the window".to_string(),
+            None => "no graphics queue".to_string(),
+        });
+    }
+    None
+}
+
+//Higher is preferred
+pub fn score(physical_device: PhysicalDevice) -> u32 {
+    type_score(physical_device.ty())
+}
+
+fn type_score(ty: PhysicalDeviceType) -> u32 {
+    match ty {
+        PhysicalDeviceType::DiscreteGpu => 4,
+        PhysicalDeviceType::IntegratedGpu => 3,
+        PhysicalDeviceType::VirtualGpu => 2,
+        PhysicalDeviceType::Cpu => 1,
+        PhysicalDeviceType::Other => 0,
+    }
+}
+
+//The chosen device when there is a choice, from `choice` or else the environment variable. Otherwise the
+//best scoring suitable device, the first one listed on ties.
+pub fn choose<'a>(instance: &'a Arc<Instance>, surface: Option<&Surface<Window>>, choice: Option<&GpuChoice>,
+    features: &Features) -> Result<PhysicalDevice<'a>, String> {
+
+    if let Some(choice) = choice.cloned().or_else(GpuChoice::from_env) {
+        let physical_device = PhysicalDevice::enumerate(instance).find(|&p| choice.matches(p))
+            .ok_or_else(|| format!("no device matches {:?}, see --list-gpus", choice))?;
+        return match unsuitable(physical_device, surface, features) {
+            Some(reason) => Err(format!("device {} can't be used: {}", physical_device.name(), reason)),
+            None => Ok(physical_device),
+        };
+    }
+
+    let mut best: Option<PhysicalDevice> = None;
+    let mut rejected = Vec::new();
+    for physical_device in PhysicalDevice::enumerate(instance) {
+        if let Some(reason) = unsuitable(physical_device, surface, features) {
+            rejected.push(format!("{}: {}", physical_device.name(), reason));
+        } else if best.map_or(true, |best| score(physical_device) > score(best)) {
+            best = Some(physical_device);
+        }
+    }
+    best.ok_or_else(|| match surface {
+        _ if rejected.is_empty() => "no Vulkan devices found".to_string(),
+        Some(_) => format!("no device supports the window surface ({})", rejected.join("; ")),
+        None => format!("no suitable device ({})", rejected.join("; ")),
+    })
+}
+
+//Properties and limits shown by --list-gpus, judged with the default options
+pub fn describe(physical_device: PhysicalDevice) -> String {
+    let limits = physical_device.limits();
+    let memory = physical_device.memory_heaps().filter(|heap| heap.is_device_local())
+        .map(|heap| heap.size()).sum::<usize>();
+    //The standard Vulkan version encoding, some vendors pack their driver versions differently
+    let driver = physical_device.driver_version();
+
+    let mut lines = vec![
+        format!("{}: {}", physical_device.index(), physical_device.name()),
+        format!("    Type: {:?}", physical_device.ty()),
+        format!("    Vulkan: {}", physical_device.api_version()),
+        format!("    Driver: {}.{}.{}", driver >> 22, (driver >> 12) & 0x3ff, driver & 0xfff),
+        format!("    Vendor: {:#06x}, device: {:#06x}", physical_device.pci_vendor_id(), physical_device.pci_device_id()),
+        format!("    Device local memory: {} MiB", memory / (1024 * 1024)),
+        format!("    Max 2D image size: {}", limits.max_image_dimension_2d()),
+        format!("    Max anisotropy: {}", limits.max_sampler_anisotropy()),
+        format!("    Max bound descriptor sets: {}", limits.max_bound_descriptor_sets()),
+        format!("    Max uniform buffer range: {}", limits.max_uniform_buffer_range()),
+        format!("    Max push constants size: {}", limits.max_push_constants_size()),
+        format!("    Point size range: {:?}", limits.point_size_range()),
+    ];
+    for family in physical_device.queue_families() {
+        let capabilities = [
+            (family.supports_graphics(), "graphics"),
+            (family.supports_compute(), "compute"),
+            (family.supports_transfers(), "transfer"),
+        ];
+        let capabilities = capabilities.iter().filter(|&&(supported, _)| supported).map(|&(_, name)| name)
+            .collect::<Vec<_>>();
+        lines.push(format!("    Queue family {}: {} queues, {}", family.id(), family.queues_count(), capabilities.join(", ")));
+    }
+    lines.push(match unsuitable(physical_device, None, &required_features(&SamplerOptions::default())) {
+        Some(reason) => format!("    Unsuitable: {}", reason),
+        None => format!("    Score: {}", score(physical_device)),
+    });
+    lines.join("\n")
+}
+
+//Every device, without a window to check presentation support against
+pub fn list_gpus() -> Result<Vec<String>, InstanceCreationError> {
+    let info = app_info_from_cargo_toml!();
+    let instance = Instance::new(Some(&info), &InstanceExtensions::none(), None)?;
+    Ok(PhysicalDevice::enumerate(&instance).map(describe).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_choose_by_index_and_anything_else_by_name() {
+        assert_eq!(GpuChoice::parse("0"), GpuChoice::Index(0));
+        assert_eq!(GpuChoice::parse("12"), GpuChoice::Index(12));
+        assert_eq!(GpuChoice::parse("GeForce RTX"), GpuChoice::Name("geforce rtx".to_string()));
+        assert_eq!(GpuChoice::parse("-1"), GpuChoice::Name("-1".to_string()));
+        assert_eq!(GpuChoice::parse("llvmpipe 2"), GpuChoice::Name("llvmpipe 2".to_string()));
+    }
+
+    #[test]
+    fn discrete_gpus_score_highest() {
+        let order = [
+            PhysicalDeviceType::DiscreteGpu,
+            PhysicalDeviceType::IntegratedGpu,
+            PhysicalDeviceType::VirtualGpu,
+            PhysicalDeviceType::Cpu,
+            PhysicalDeviceType::Other,
+        ];
+        for pair in order.windows(2) {
+            assert!(type_score(pair[0]) > type_score(pair[1]), "{:?} should beat {:?}", pair[0], pair[1]);
+        }
+    }
+
+    #[test]
+    fn anisotropy_is_only_required_when_turned_on() {
+        let off = SamplerOptions { anisotropy: 1.0, ..SamplerOptions::default() };
+        assert!(!required_features(&off).sampler_anisotropy);
+        assert!(required_features(&SamplerOptions::default()).sampler_anisotropy);
+        assert!(required_features(&off).large_points);
+        assert!(!required_features(&off).wide_lines);
+    }
+
+    #[test]
+    fn missing_features_are_named() {
+        let required = required_features(&SamplerOptions::default());
+        assert_eq!(missing_features(&Features::none(), &required), vec!["sampler_anisotropy", "large_points"]);
+        let points = Features { large_points: true, ..Features::none() };
+        assert_eq!(missing_features(&points, &required), vec!["sampler_anisotropy"]);
+        assert!(missing_features(&required, &required).is_empty());
+        assert!(missing_features(&Features::none(), &Features::none()).is_empty());
+    }
+}
@@ -0,0 +1,110 @@
+use super::{camera, gpu, input, light, mesh, mtl, validation, RendererError, RendererOptions, CLEAR_COLOR};
+use super::{draw_scene, dynamic_state, gen_pipelines, gen_render_pass, gen_sampler, upload_scene, vertex_uniforms};
+use super::shaders::Shaders;
+use super::texture::SamplerOptions;
+use cgmath::{Matrix4, SquareMatrix};
+use image::RgbaImage;
+use std::error::Error;
+use std::path::Path;
+use std::sync::Arc;
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+use vulkano::command_buffer::AutoCommandBufferBuilder;
+use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
+use vulkano::device::Device;
+use vulkano::format::Format;
+use vulkano::framebuffer::Framebuffer;
+use vulkano::image::{AttachmentImage, ImageUsage};
+use vulkano::instance::InstanceExtensions;
+use vulkano::sync::GpuFuture;
+
+//PNG stores sRGB encoded pixels, the same as the color attachment
+const COLOR_FORMAT: Format = Format::R8G8B8A8Srgb;
+pub const DEFAULT_SIZE: [u32; 2] = [800, 600];
+
+pub fn render_to_png(scene: &mesh::Model, fallback_material: &mtl::Material, options: &RendererOptions,
+    dimensions: [u32; 2], output: &Path) -> Result<(), Box<Error>> {
+
+    let (queues, validation) = init_headless(options.gpu.as_ref(), &options.validation, &options.sampler)?;
+    let image = render(&queues, scene, fallback_material, &options.sampler, dimensions)?;
+    validation.check();
+    image.save(output)?;
+    Ok(())
+}
+
+//Renders a single frame with the same render pass and pipelines as the window, framed by the default
+//orbit camera
+pub fn render(queues: &gpu::Queues, scene: &mesh::Model, fallback_material: &mtl::Material,
+    sampler_options: &SamplerOptions, dimensions: [u32; 2]) -> Result<RgbaImage, Box<Error>> {
+
+    let queue = queues.graphics.clone();
+    let device = queue.device().clone();
+
+    let render_pass = gen_render_pass(device.clone(), COLOR_FORMAT)?;
+    let color = AttachmentImage::with_usage(device.clone(), dimensions, COLOR_FORMAT,
+        ImageUsage { transfer_source: true, ..ImageUsage::none() })?;
+    let depth = AttachmentImage::transient(device.clone(), dimensions, Format::D16Unorm)?;
+    let framebuffer = Arc::new(Framebuffer::start(render_pass.clone())
+        .add(color.clone())?
+        .add(depth)?
+        .build()?);
+
+    let shaders = Shaders::builtin(device.clone())?;
+    let pipelines = gen_pipelines(render_pass, device.clone(), &shaders)?;
+
+    let sampler = gen_sampler(device.clone(), sampler_options)?;
+    let (scene_buffers, upload_future) = upload_scene(queues, scene, fallback_material,
+        pipelines.triangles.clone(), sampler)?;
+
+    let bounds = scene.bounds().ok_or("Cannot frame an empty model")?;
+    let controls = input::Controls::orbiting(bounds);
+    let camera = camera::Camera::perspective(controls.eye(), bounds.center());
+    let aspect = dimensions[0] as f32 / dimensions[1] as f32;
+    let uniforms = CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::uniform_buffer(),
+        vertex_uniforms(Matrix4::<f32>::identity(), camera.view(), camera.projection(aspect)))?;
+    let lights = CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::uniform_buffer(),
+        light::uniforms(&scene.lighting))?;
+    let set = Arc::new(PersistentDescriptorSet::start(pipelines.triangles.clone(), 0)
+        .add_buffer(uniforms)?
+        .add_buffer(lights)?
+        .build()?);
+
+    let pixel_count = dimensions[0] as usize * dimensions[1] as usize * 4;
+    let pixels = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::transfer_destination(),
+        (0..pixel_count).map(|_| 0u8))?;
+
+    let builder = AutoCommandBufferBuilder::primary_one_time_submit(device.clone(), queue.family())?
+        .begin_render_pass(framebuffer, false, vec!(CLEAR_COLOR.into(), 1f32.into()))?;
+    let command_buffer = draw_scene(builder, &pipelines, &dynamic_state(dimensions), &scene_buffers, set)?
+        .end_render_pass()?
+        .copy_image_to_buffer(color, pixels.clone())?
+        .build()?;
+
+    upload_future
+        .then_execute(queue, command_buffer)?
+        .then_signal_fence_and_flush()?
+        .wait(None)?;
+
+    let content = pixels.read()?;
+    let image = RgbaImage::from_raw(dimensions[0], dimensions[1], content.to_vec())
+        .ok_or("Rendered frame has the wrong size")?;
+    Ok(image)
+}
+
+//Without a surface neither the window system extensions nor a swapchain are needed, so this also works
+//with software drivers like lavapipe on machines without a display. Validation stays active while the
+//returned Validation lives. The device only gets the features needed to render with `sampler_options`.
+pub fn init_headless(gpu: Option<&gpu::GpuChoice>, validation_options: &validation::ValidationOptions,
+    sampler_options: &SamplerOptions) -> Result<(gpu::Queues, validation::Validation), RendererError> {
+
+    let (instance, validation) = validation::create_instance(&InstanceExtensions::none(), validation_options)?;
+
+    let features = gpu::required_features(sampler_options);
+    let physical_device = gpu::choose(&instance, None, gpu, &features).map_err(RendererError::NoDevice)?;
+    let queue_family = gpu::queue_family(physical_device, None).ok_or(RendererError::NoQueue)?;
+
+    let requests = gpu::QueueRequests::new(physical_device, queue_family);
+    let (_, queues) = Device::new(physical_device, &features, &gpu::required_extensions(None),
+        requests.families.iter().cloned())?;
+    let queues = requests.queues(queues).ok_or(RendererError::NoQueue)?;
+    Ok((queues, validation))
+}
@@ -0,0 +1,78 @@
+use super::shaders::Shaders;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use vulkano::device::Device;
+
+//Checking modification times every frame would stat both files hundreds of times a second
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+//Recompiles the shader sources in the crate's source tree when they change on disk
+pub struct ShaderWatcher {
+    compiler: shaderc::Compiler,
+    vertex: PathBuf,
+    fragment: PathBuf,
+    modified: [Option<SystemTime>; 2],
+    last_poll: Instant,
+}
+
+impl ShaderWatcher {
+    //None when shaderc can't create a compiler
+    pub fn new() -> Option<ShaderWatcher> {
+        let mut watcher = ShaderWatcher {
+            compiler: shaderc::Compiler::new()?,
+            vertex: PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/src/vertex.glsl")),
+            fragment: PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/src/frag.glsl")),
+            modified: [None, None],
+            last_poll: Instant::now(),
+        };
+        //The compiled in shaders match the files as they are now
+        watcher.modified = watcher.modification_times();
+        info!("Watching {} and {} for changes", watcher.vertex.display(), watcher.fragment.display());
+        Some(watcher)
+    }
+
+    //Whether either file was written since the last call
+    pub fn changed(&mut self) -> bool {
+        if self.last_poll.elapsed() < POLL_INTERVAL {
+            return false;
+        }
+        self.last_poll = Instant::now();
+
+        let modified = self.modification_times();
+        let changed = modified != self.modified;
+        self.modified = modified;
+        changed
+    }
+
+    //The error is the compiler output, ready to be shown to whoever is editing the shaders
+    pub fn compile(&mut self, device: Arc<Device>) -> Result<Shaders, String> {
+        let vertex = self.compile_file(true)?;
+        let fragment = self.compile_file(false)?;
+        //shaderc only emits valid SPIR-V, from_words rejects an edit that changes the interface
+        unsafe { Shaders::from_words(device, &vertex, &fragment) }
+    }
+
+    fn compile_file(&mut self, vertex: bool) -> Result<Vec<u32>, String> {
+        let (path, kind) = if vertex {
+            (&self.vertex, shaderc::ShaderKind::Vertex)
+        } else {
+            (&self.fragment, shaderc::ShaderKind::Fragment)
+        };
+        let source = fs::read_to_string(path).map_err(|err| format!("could not read {}: {}", path.display(), err))?;
+        let name = path.display().to_string();
+
+        let artifact = self.compiler.compile_into_spirv(&source, kind, &name, "main", None)
+            .map_err(|err| err.to_string())?;
+        if artifact.get_num_warnings() > 0 {
+            warn!("{}", artifact.get_warning_messages());
+        }
+        Ok(artifact.as_binary().to_vec())
+    }
+
+    fn modification_times(&self) -> [Option<SystemTime>; 2] {
+        let modified = |path: &PathBuf| fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+        [modified(&self.vertex), modified(&self.fragment)]
+    }
+}
@@ -0,0 +1,406 @@
+use cgmath::{Deg, InnerSpace, Point3, Rad, Vector3};
+use winit::{ElementState, Event, KeyboardInput, MouseButton, MouseScrollDelta, VirtualKeyCode, WindowEvent};
+
+use super::camera::{Bounds, Camera};
+
+//Radians of rotation per logical pixel of mouse drag
+const ROTATE_SPEED: f32 = 0.005;
+//Fraction of the orbit distance removed per scrolled line
+const ZOOM_STEP: f32 = 0.1;
+const MAX_PITCH: Deg<f32> = Deg(89.0);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CameraMode {
+    //Arcball around a target point, the mouse drag rotates and the scroll wheel zooms
+    Orbit,
+    //Free look with WASD + Space/LShift, the mouse drag looks around and the scroll wheel changes speed
+    Fly,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MoveKeys {
+    pub forward: bool,
+    pub back: bool,
+    pub left: bool,
+    pub right: bool,
+    pub up: bool,
+    pub down: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Controls {
+    pub mode: CameraMode,
+    pub bounds: Bounds,
+    //Orbit state
+    pub target: Point3<f32>,
+    pub distance: f32,
+    //Fly state, in world units per second
+    pub position: Point3<f32>,
+    pub speed: f32,
+    //Orientation shared by both modes so switching keeps the current view
+    pub yaw: Rad<f32>,
+    pub pitch: Rad<f32>,
+    pub keys: MoveKeys,
+    pub dragging: bool,
+    pub cursor: Option<(f64, f64)>,
+}
+
+impl Controls {
+    pub fn orbiting(bounds: Bounds) -> Self {
+        let radius = bounds.radius().max(std::f32::EPSILON);
+        let mut controls = Controls {
+            mode: CameraMode::Orbit,
+            bounds,
+            target: bounds.center(),
+            //Far enough back that the bounding sphere fits in a 45 degree field of view
+            distance: radius * 2.5,
+            position: bounds.center(),
+            speed: radius,
+            yaw: Rad(0.0),
+            pitch: Deg(-15.0).into(),
+            keys: MoveKeys::default(),
+            dragging: false,
+            cursor: None,
+        };
+        controls.position = controls.eye();
+        controls
+    }
+
+    //Unit vector the camera is looking along
+    pub fn forward(&self) -> Vector3<f32> {
+        let (sy, cy) = self.yaw.0.sin_cos();
+        let (sp, cp) = self.pitch.0.sin_cos();
+        Vector3::new(-sy * cp, sp, -cy * cp)
+    }
+
+    pub fn eye(&self) -> Point3<f32> {
+        match self.mode {
+            CameraMode::Orbit => self.target - self.forward() * self.distance,
+            CameraMode::Fly => self.position,
+        }
+    }
+
+    pub fn apply(&self, camera: &mut Camera) {
+        let eye = self.eye();
+        camera.eye = eye;
+        camera.target = eye + self.forward();
+        camera.up = Vector3::unit_y();
+    }
+}
+
+//Maps a single window event onto a new control state. Anything that isn't camera input is passed through unchanged.
+pub fn handle_event(controls: Controls, event: &Event) -> Controls {
+    let mut next = controls;
+
+    let event = match event {
+        Event::WindowEvent { event, .. } => event,
+        _ => return next,
+    };
+
+    match event {
+        WindowEvent::KeyboardInput { input: KeyboardInput { virtual_keycode: Some(key), state, .. }, .. } => {
+            let pressed = *state == ElementState::Pressed;
+            match key {
+                VirtualKeyCode::W => next.keys.forward = pressed,
+                VirtualKeyCode::S => next.keys.back = pressed,
+                VirtualKeyCode::A => next.keys.left = pressed,
+                VirtualKeyCode::D => next.keys.right = pressed,
+                VirtualKeyCode::Space => next.keys.up = pressed,
+                VirtualKeyCode::LShift => next.keys.down = pressed,
+                VirtualKeyCode::Tab if pressed => next = switch_mode(next),
+                VirtualKeyCode::R if pressed => next = Controls { mode: next.mode, ..Controls::orbiting(next.bounds) },
+                _ => (),
+            }
+        },
+        WindowEvent::MouseInput { button: MouseButton::Left, state, .. } => {
+            next.dragging = *state == ElementState::Pressed;
+        },
+        WindowEvent::CursorMoved { position, .. } => {
+            let position = (position.x, position.y);
+            if let (true, Some(last)) = (next.dragging, next.cursor) {
+                let dx = (position.0 - last.0) as f32;
+                let dy = (position.1 - last.1) as f32;
+                next.yaw -= Rad(dx * ROTATE_SPEED);
+                next.pitch = clamp_pitch(next.pitch - Rad(dy * ROTATE_SPEED));
+            }
+            next.cursor = Some(position);
+        },
+        WindowEvent::CursorLeft { .. } => {
+            next.cursor = None;
+            next.dragging = false;
+        },
+        WindowEvent::MouseWheel { delta, .. } => {
+            let lines = match delta {
+                MouseScrollDelta::LineDelta(_, y) => *y,
+                MouseScrollDelta::PixelDelta(position) => position.y as f32 / 20.0,
+            };
+            match next.mode {
+                CameraMode::Orbit => {
+                    let min_distance = next.bounds.radius() * 0.05;
+                    next.distance = (next.distance * (1.0 - ZOOM_STEP).powf(lines)).max(min_distance);
+                },
+                CameraMode::Fly => next.speed *= (1.0 + ZOOM_STEP).powf(lines),
+            }
+        },
+        WindowEvent::Focused(false) => {
+            next.keys = MoveKeys::default();
+            next.dragging = false;
+        },
+        _ => (),
+    }
+
+    next
+}
+
+//Number keys 1 to 9 toggle the meshes of the loaded model, returns the zero based mesh index
+pub fn toggled_mesh(event: &Event) -> Option<usize> {
+    let key = match event {
+        Event::WindowEvent { event: WindowEvent::KeyboardInput { input: KeyboardInput {
+            virtual_keycode: Some(key), state: ElementState::Pressed, .. }, .. }, .. } => *key,
+        _ => return None,
+    };
+
+    let keys = [
+        VirtualKeyCode::Key1, VirtualKeyCode::Key2, VirtualKeyCode::Key3,
+        VirtualKeyCode::Key4, VirtualKeyCode::Key5, VirtualKeyCode::Key6,
+        VirtualKeyCode::Key7, VirtualKeyCode::Key8, VirtualKeyCode::Key9,
+    ];
+    keys.iter().position(|&k| k == key)
+}
+
+//The V key switches to the next present policy
+pub fn present_policy_cycled(event: &Event) -> bool {
+    match event {
+        Event::WindowEvent { event: WindowEvent::KeyboardInput { input: KeyboardInput {
+            virtual_keycode: Some(VirtualKeyCode::V), state: ElementState::Pressed, .. }, .. }, .. } => true,
+        _ => false,
+    }
+}
+
+//Moves the fly camera by however long the movement keys have been held. Orbit mode doesn't move on its own.
+pub fn advance(controls: Controls, dt: f32) -> Controls {
+    if controls.mode != CameraMode::Fly {
+        return controls;
+    }
+
+    let forward = controls.forward();
+    let right = forward.cross(Vector3::unit_y()).normalize();
+    let keys = controls.keys;
+    let axis = |pos: bool, neg: bool| (pos as i32 - neg as i32) as f32;
+
+    let direction = forward * axis(keys.forward, keys.back)
+        + right * axis(keys.right, keys.left)
+        + Vector3::unit_y() * axis(keys.up, keys.down);
+
+    if direction.magnitude2() == 0.0 {
+        return controls;
+    }
+
+    Controls {
+        position: controls.position + direction.normalize() * controls.speed * dt,
+        ..controls
+    }
+}
+
+fn switch_mode(controls: Controls) -> Controls {
+    match controls.mode {
+        CameraMode::Orbit => Controls {
+            mode: CameraMode::Fly,
+            position: controls.eye(),
+            ..controls
+        },
+        //Keep the view direction and orbit around whatever is straight ahead at the old distance
+        CameraMode::Fly => Controls {
+            mode: CameraMode::Orbit,
+            target: controls.position + controls.forward() * controls.distance,
+            keys: MoveKeys::default(),
+            ..controls
+        },
+    }
+}
+
+fn clamp_pitch(pitch: Rad<f32>) -> Rad<f32> {
+    let max: Rad<f32> = MAX_PITCH.into();
+    Rad(pitch.0.max(-max.0).min(max.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use winit::{DeviceId, ModifiersState, TouchPhase, WindowId};
+    use winit::dpi::LogicalPosition;
+
+    const EPSILON: f32 = 1e-5;
+
+    fn controls() -> Controls {
+        Controls::orbiting(Bounds { min: Point3::new(-1.0, -1.0, -1.0), max: Point3::new(1.0, 1.0, 1.0) })
+    }
+
+    //The ids are only compared, never handed to winit
+    fn window_event(event: WindowEvent) -> Event {
+        Event::WindowEvent { window_id: unsafe { WindowId::dummy() }, event }
+    }
+
+    fn key(key: VirtualKeyCode, state: ElementState) -> Event {
+        window_event(WindowEvent::KeyboardInput {
+            device_id: unsafe { DeviceId::dummy() },
+            input: KeyboardInput { scancode: 0, state, virtual_keycode: Some(key), modifiers: ModifiersState::default() },
+        })
+    }
+
+    fn left_button(state: ElementState) -> Event {
+        window_event(WindowEvent::MouseInput {
+            device_id: unsafe { DeviceId::dummy() },
+            state,
+            button: MouseButton::Left,
+            modifiers: ModifiersState::default(),
+        })
+    }
+
+    fn cursor_moved(x: f64, y: f64) -> Event {
+        window_event(WindowEvent::CursorMoved {
+            device_id: unsafe { DeviceId::dummy() },
+            position: LogicalPosition::new(x, y),
+            modifiers: ModifiersState::default(),
+        })
+    }
+
+    fn scrolled(lines: f32) -> Event {
+        window_event(WindowEvent::MouseWheel {
+            device_id: unsafe { DeviceId::dummy() },
+            delta: MouseScrollDelta::LineDelta(0.0, lines),
+            phase: TouchPhase::Moved,
+            modifiers: ModifiersState::default(),
+        })
+    }
+
+    fn handle_events(controls: Controls, events: &[Event]) -> Controls {
+        events.iter().fold(controls, handle_event)
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < EPSILON, "{} is not {}", actual, expected);
+    }
+
+    #[test]
+    fn w_holds_the_forward_key() {
+        let pressed = handle_event(controls(), &key(VirtualKeyCode::W, ElementState::Pressed));
+        assert_eq!(pressed.keys, MoveKeys { forward: true, ..MoveKeys::default() });
+        let released = handle_event(pressed, &key(VirtualKeyCode::W, ElementState::Released));
+        assert_eq!(released.keys, MoveKeys::default());
+    }
+
+    #[test]
+    fn tab_switches_modes_and_keeps_the_view() {
+        let orbit = controls();
+        let fly = handle_event(orbit, &key(VirtualKeyCode::Tab, ElementState::Pressed));
+        assert_eq!(fly.mode, CameraMode::Fly);
+        assert_eq!(fly.eye(), orbit.eye());
+        assert_eq!(fly.forward(), orbit.forward());
+
+        //Only the press switches
+        let released = handle_event(fly, &key(VirtualKeyCode::Tab, ElementState::Released));
+        assert_eq!(released.mode, CameraMode::Fly);
+
+        let back = handle_event(released, &key(VirtualKeyCode::Tab, ElementState::Pressed));
+        assert_eq!(back.mode, CameraMode::Orbit);
+        assert!((back.eye() - orbit.eye()).magnitude() < EPSILON);
+    }
+
+    #[test]
+    fn dragging_rotates() {
+        let start = controls();
+        let next = handle_events(start, &[
+            left_button(ElementState::Pressed),
+            cursor_moved(100.0, 100.0),
+            cursor_moved(110.0, 90.0),
+        ]);
+        assert!(next.dragging);
+        assert_eq!(next.cursor, Some((110.0, 90.0)));
+        assert_close(next.yaw.0, start.yaw.0 - 10.0 * ROTATE_SPEED);
+        assert_close(next.pitch.0, start.pitch.0 + 10.0 * ROTATE_SPEED);
+    }
+
+    #[test]
+    fn moving_without_dragging_only_tracks_the_cursor() {
+        let start = controls();
+        let next = handle_events(start, &[cursor_moved(100.0, 100.0), cursor_moved(150.0, 20.0)]);
+        assert_eq!(next.cursor, Some((150.0, 20.0)));
+        assert_eq!((next.yaw, next.pitch), (start.yaw, start.pitch));
+
+        let released = handle_events(next, &[
+            left_button(ElementState::Pressed),
+            left_button(ElementState::Released),
+            cursor_moved(0.0, 0.0),
+        ]);
+        assert_eq!((released.yaw, released.pitch), (start.yaw, start.pitch));
+    }
+
+    #[test]
+    fn pitch_stops_at_89_degrees() {
+        let max: Rad<f32> = MAX_PITCH.into();
+        let down = [left_button(ElementState::Pressed), cursor_moved(0.0, 0.0), cursor_moved(0.0, 100_000.0)];
+        assert_close(handle_events(controls(), &down).pitch.0, -max.0);
+        let up = [left_button(ElementState::Pressed), cursor_moved(0.0, 0.0), cursor_moved(0.0, -100_000.0)];
+        assert_close(handle_events(controls(), &up).pitch.0, max.0);
+    }
+
+    #[test]
+    fn wheel_zooms_in_orbit_mode() {
+        let start = controls();
+        let next = handle_event(start, &scrolled(1.0));
+        assert_close(next.distance, start.distance * (1.0 - ZOOM_STEP));
+        assert_eq!(next.speed, start.speed);
+
+        //Never closer than a fraction of the model's size
+        let closest = handle_event(start, &scrolled(1000.0));
+        assert_close(closest.distance, start.bounds.radius() * 0.05);
+    }
+
+    #[test]
+    fn wheel_changes_the_speed_in_fly_mode() {
+        let start = handle_event(controls(), &key(VirtualKeyCode::Tab, ElementState::Pressed));
+        let next = handle_event(start, &scrolled(2.0));
+        assert_close(next.speed, start.speed * (1.0 + ZOOM_STEP).powi(2));
+        assert_eq!(next.distance, start.distance);
+        assert_eq!(next.position, start.position);
+    }
+
+    #[test]
+    fn losing_focus_releases_everything() {
+        let held = handle_events(controls(), &[
+            key(VirtualKeyCode::W, ElementState::Pressed),
+            key(VirtualKeyCode::Space, ElementState::Pressed),
+            left_button(ElementState::Pressed),
+        ]);
+        assert!(held.keys.forward && held.keys.up && held.dragging);
+
+        let unfocused = handle_event(held, &window_event(WindowEvent::Focused(false)));
+        assert_eq!(unfocused.keys, MoveKeys::default());
+        assert!(!unfocused.dragging);
+    }
+
+    #[test]
+    fn advance_only_moves_in_fly_mode() {
+        let orbit = handle_event(controls(), &key(VirtualKeyCode::W, ElementState::Pressed));
+        assert_eq!(advance(orbit, 1.0), orbit);
+
+        let fly = handle_event(orbit, &key(VirtualKeyCode::Tab, ElementState::Pressed));
+        let moved = advance(fly, 0.5);
+        let expected = fly.position + fly.forward() * fly.speed * 0.5;
+        assert!((moved.position - expected).magnitude() < EPSILON);
+    }
+
+    #[test]
+    fn advance_without_keys_stays_put() {
+        let fly = handle_event(controls(), &key(VirtualKeyCode::Tab, ElementState::Pressed));
+        assert_eq!(advance(fly, 1.0), fly);
+
+        //Opposite keys cancel out
+        let both = handle_events(fly, &[
+            key(VirtualKeyCode::A, ElementState::Pressed),
+            key(VirtualKeyCode::D, ElementState::Pressed),
+        ]);
+        assert_eq!(advance(both, 1.0).position, fly.position);
+    }
+}
@@ -0,0 +1,1005 @@
+#[macro_use]
+extern crate vulkano;
+extern crate vulkano_shaders;
+extern crate winit;
+extern crate vulkano_win;
+extern crate image;
+extern crate cgmath;
+#[cfg(feature = "hot-reload")]
+extern crate shaderc;
+#[macro_use]
+extern crate log;
+
+use std::cmp::{min, max};
+use std::mem;
+use std::vec::Vec;
+use std::sync::Arc;
+use std::error::Error;
+use std::path::PathBuf;
+use std::time::Instant;
+use winit::{WindowBuilder, EventsLoop, Window};
+use cgmath::{Matrix, Matrix4, SquareMatrix};
+use vulkano::sampler::{Sampler, Filter, SamplerCreationError};
+use vulkano::image::{ImageLayout, ImageUsage, MipmapsCount, Dimensions};
+use vulkano::image::immutable::{ImmutableImage, ImmutableImageInitialization};
+use vulkano_win::VkSurfaceBuild;
+use vulkano::device::{Device, Queue};
+use vulkano::swapchain::{AcquireError, Surface, Swapchain, SwapchainCreationError};
+use vulkano::descriptor::descriptor_set::{DescriptorSet, DescriptorSetsCollection, PersistentDescriptorSet};
+use vulkano::image::{AttachmentImage, StorageImage, swapchain::SwapchainImage};
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer, ImmutableBuffer};
+use vulkano::memory::DeviceMemoryAllocError;
+use vulkano::pipeline::{GraphicsPipelineAbstract, viewport::{Scissor, Viewport},
+    vertex::SingleBufferDefinition, GraphicsPipeline};
+use vulkano::pipeline::input_assembly::{Index, PrimitiveTopology};
+use vulkano::framebuffer::{Framebuffer, FramebufferAbstract, FramebufferCreationError, RenderPassAbstract,
+    RenderPassCreationError, Subpass};
+use vulkano::format::Format;
+use vulkano::command_buffer::{CommandBuffer, CommandBufferExecFuture, AutoCommandBuffer, AutoCommandBufferBuilder,
+    DrawIndexedError, DynamicState};
+use vulkano::sync;
+use vulkano::sync::{NowFuture, FenceSignalFuture, FlushError, GpuFuture};
+
+use camera::Camera;
+use error::RendererError;
+use gpu::{GpuChoice, Queues};
+use light::Lighting;
+use mesh::{Mesh, Model};
+use mtl::Material;
+use present::PresentPolicy;
+use shaders::Shaders;
+use stats::FrameStats;
+use texture::SamplerOptions;
+use validation::{Validation, ValidationOptions};
+
+pub mod camera;
+pub mod error;
+pub mod golden;
+pub mod gpu;
+pub mod headless;
+#[cfg(feature = "hot-reload")]
+mod hot_reload;
+pub mod input;
+pub mod light;
+pub mod load;
+pub mod mesh;
+pub mod mtl;
+pub mod objload;
+pub mod present;
+#[cfg(feature = "hot-reload")]
+mod reflect;
+mod shaders;
+pub mod stats;
+mod teapot;
+pub mod texture;
+pub mod validation;
+
+const CLEAR_COLOR: [f32; 4] = [0.0, 0.3, 0.6, 1.0];
+pub const WINDOW_TITLE: &str = "Riley's Vulkan Render Engine";
+//Lets the CPU record the next frame while the GPU draws the current one
+pub const DEFAULT_FRAMES_IN_FLIGHT: usize = 2;
+
+#[derive(Clone, Debug)]
+pub struct Vertex {
+    position: (f32, f32, f32),
+} vulkano::impl_vertex!(Vertex, position);
+
+#[derive(Clone, Debug)]
+pub struct Normal {
+    normal: (f32, f32, f32),
+} vulkano::impl_vertex!(Normal, normal);
+
+#[derive(Clone, Debug)]
+pub struct TexVert {
+    position2D: (f32, f32),
+} vulkano::impl_vertex!(TexVert, position2D); 
+
+//Interleaved vertex used for everything that is drawn, built from the separate streams above
+#[derive(Clone, Debug)]
+pub struct ModelVertex {
+    pub position: (f32, f32, f32),
+    pub normal: (f32, f32, f32),
+    pub tex_coord: (f32, f32),
+} vulkano::impl_vertex!(ModelVertex, position, normal, tex_coord);
+
+//u16 indices whenever every vertex of the mesh can be addressed with them, u32 otherwise
+#[derive(Clone, Debug)]
+pub enum Indices {
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+}
+
+impl Indices {
+    pub fn new(indices: Vec<u32>, vertex_count: usize) -> Self {
+        if vertex_count <= u16::max_value() as usize + 1 {
+            Indices::U16(indices.into_iter().map(|i| i as u16).collect())
+        } else {
+            Indices::U32(indices)
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Indices::U16(indices) => indices.len(),
+            Indices::U32(indices) => indices.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+//One pipeline per primitive topology a mesh can contain
+#[derive(Clone)]
+struct Pipelines {
+    triangles: Arc<GraphicsPipelineAbstract + Send + Sync>,
+    lines: Arc<GraphicsPipelineAbstract + Send + Sync>,
+    points: Arc<GraphicsPipelineAbstract + Send + Sync>,
+}
+
+enum IndexBuffer {
+    U16(Arc<ImmutableBuffer<[u16]>>),
+    U32(Arc<ImmutableBuffer<[u32]>>),
+}
+
+impl IndexBuffer {
+    //Empty batches have no buffer, Vulkan doesn't allow zero sized buffers
+    fn new(queues: &Queues, indices: &Indices, futures: &mut Vec<Box<GpuFuture>>)
+        -> Result<Option<Self>, DeviceMemoryAllocError> {
+
+        if indices.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(match indices {
+            Indices::U16(indices) => IndexBuffer::U16(upload_buffer(queues, BufferUsage::index_buffer(), indices, futures)?),
+            Indices::U32(indices) => IndexBuffer::U32(upload_buffer(queues, BufferUsage::index_buffer(), indices, futures)?),
+        }))
+    }
+}
+
+//Device local buffers, filled from staging buffers on the transfer queue
+struct MeshBuffers {
+    vertices: Arc<ImmutableBuffer<[ModelVertex]>>,
+    triangles: Option<IndexBuffer>,
+    lines: Option<IndexBuffer>,
+    points: Option<IndexBuffer>,
+}
+
+impl MeshBuffers {
+    //The returned future finishes once every buffer has been copied
+    fn new(queues: &Queues, mesh: &Mesh) -> Result<(Self, Box<GpuFuture>), DeviceMemoryAllocError> {
+        let mut futures = Vec::new();
+        let buffers = MeshBuffers {
+            vertices: upload_buffer(queues, BufferUsage::vertex_buffer(), &mesh.vertices, &mut futures)?,
+            triangles: IndexBuffer::new(queues, &mesh.indices, &mut futures)?,
+            lines: IndexBuffer::new(queues, &mesh.lines, &mut futures)?,
+            points: IndexBuffer::new(queues, &mesh.points, &mut futures)?,
+        };
+        let future = futures.into_iter().fold(Box::new(sync::now(queues.graphics.device().clone())) as Box<GpuFuture>,
+            |joined, future| Box::new(joined.join(future)));
+        Ok((buffers, future))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RendererOptions {
+    pub sampler: SamplerOptions,
+    //Overrides the automatic device choice, see gpu::choose
+    pub gpu: Option<GpuChoice>,
+    pub validation: ValidationOptions,
+    //Can be changed later with Renderer::set_present_policy
+    pub present: PresentPolicy,
+    //How many frames the CPU may get ahead of the GPU, at least 1
+    pub frames_in_flight: usize,
+    //Rebuild the pipelines when vertex.glsl or frag.glsl change, needs the hot-reload feature
+    pub watch_shaders: bool,
+}
+
+impl Default for RendererOptions {
+    fn default() -> Self {
+        RendererOptions {
+            sampler: SamplerOptions::default(),
+            gpu: None,
+            validation: ValidationOptions::default(),
+            present: PresentPolicy::default(),
+            frames_in_flight: DEFAULT_FRAMES_IN_FLIGHT,
+            watch_shaders: false,
+        }
+    }
+}
+
+//Signaled once the GPU is done with a frame. Shared, because the next frame is chained onto it while
+//its slot keeps it to wait on before reuse.
+type FrameFence = Arc<FenceSignalFuture<Box<GpuFuture>>>;
+
+//What one frame in flight writes to. Only touched again after the fence of the frame that last used
+//it has signaled.
+struct Frame {
+    uniforms: Arc<CpuAccessibleBuffer<vertex::ty::Data>>,
+    lights: Arc<CpuAccessibleBuffer<frag::ty::Lights>>,
+    set: Arc<DescriptorSet + Send + Sync>,
+    fence: Option<FrameFence>,
+}
+
+//Owns everything needed to draw uploaded meshes into a window
+pub struct Renderer {
+    device: Arc<Device>,
+    queues: Queues,
+    surface: Arc<Surface<Window>>,
+    swapchain: Arc<Swapchain<Window>>,
+    present_policy: PresentPolicy,
+    render_pass: Arc<RenderPassAbstract + Send + Sync>,
+    //Built once, the viewport and scissor are set per frame so resizing doesn't rebuild them
+    pipelines: Pipelines,
+    #[cfg(feature = "hot-reload")]
+    shader_watcher: Option<hot_reload::ShaderWatcher>,
+    framebuffers: Vec<Arc<FramebufferAbstract + Send + Sync>>,
+    frames: Vec<Frame>,
+    //Copied into the frame's light buffer every frame, so changes show up on the next one
+    lighting: Lighting,
+    //Index into frames of the next frame to draw
+    frame: usize,
+    stats: FrameStats,
+    sampler: Arc<Sampler>,
+    blank_texture: Arc<ImmutableImage<Format>>,
+    scene: SceneBuffers,
+    //The last submitted frame, which the next one is chained onto
+    previous_frame_end: Option<FrameFence>,
+    //Uploads that haven't been submitted with a frame yet
+    pending_uploads: Box<GpuFuture>,
+    recreate_swapchain: bool,
+    //Last, so messages about destroying everything above are still reported
+    validation: Validation,
+}
+
+impl Renderer {
+    //Opens the window on the given events loop, which the caller keeps polling
+    pub fn new(events_loop: &EventsLoop, options: &RendererOptions) -> Result<Renderer, RendererError> {
+        let (device, queues, surface, validation) = init_vulkan(events_loop, options)?;
+
+        let (swapchain, images) = gen_swapchain(surface.clone(), queues.graphics.clone(), device.clone(),
+            options.present, None)?;
+
+        let shaders = Shaders::builtin(device.clone()).map_err(RendererError::ShaderLoad)?;
+
+        let render_pass = gen_render_pass(device.clone(), swapchain.format())?;
+        let pipelines = gen_pipelines(render_pass.clone(), device.clone(), &shaders)?;
+
+        #[cfg(feature = "hot-reload")]
+        let shader_watcher = if options.watch_shaders {
+            let watcher = hot_reload::ShaderWatcher::new();
+            if watcher.is_none() {
+                warn!("Could not create the shader compiler, shaders won't be reloaded");
+            }
+            watcher
+        } else {
+            None
+        };
+        #[cfg(not(feature = "hot-reload"))]
+        {
+            if options.watch_shaders {
+                warn!("Built without the hot-reload feature, shaders won't be reloaded");
+            }
+        }
+        let framebuffers = gen_framebuffers(&images, render_pass.clone(), device.clone())?;
+
+        let frames = gen_frames(device.clone(), pipelines.triangles.clone(), options.frames_in_flight)?;
+        let sampler = gen_sampler(device.clone(), &options.sampler)?;
+        let (blank_texture, blank_future) = gen_blank_texture(&queues)?;
+
+        Ok(Renderer {
+            device,
+            queues,
+            surface,
+            swapchain,
+            present_policy: options.present,
+            render_pass,
+            pipelines,
+            #[cfg(feature = "hot-reload")]
+            shader_watcher,
+            framebuffers,
+            frames,
+            lighting: Lighting::default(),
+            frame: 0,
+            stats: FrameStats::new(),
+            sampler,
+            blank_texture,
+            scene: SceneBuffers::new(),
+            previous_frame_end: None,
+            pending_uploads: blank_future,
+            recreate_swapchain: false,
+            validation,
+        })
+    }
+
+    pub fn window(&self) -> &Window {
+        self.surface.window()
+    }
+
+    pub fn dimensions(&self) -> [u32; 2] {
+        self.swapchain.dimensions()
+    }
+
+    pub fn frames_in_flight(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn frame_stats(&self) -> &FrameStats {
+        &self.stats
+    }
+
+    //Rebuilds the pipelines from the shader sources when they changed. On errors the previous pipelines
+    //are kept, so a typo doesn't end the session.
+    #[cfg(feature = "hot-reload")]
+    fn reload_shaders(&mut self) {
+        let shaders = match self.shader_watcher {
+            Some(ref mut watcher) => {
+                if !watcher.changed() {
+                    return;
+                }
+                watcher.compile(self.device.clone())
+            },
+            None => return,
+        };
+        let pipelines = shaders.and_then(|shaders| gen_pipelines(self.render_pass.clone(), self.device.clone(), &shaders)
+            .map_err(|err| err.to_string()));
+        match pipelines {
+            Ok(pipelines) => {
+                self.pipelines = pipelines;
+                info!("Reloaded the shaders");
+            },
+            Err(message) => error!("Keeping the previous shaders: {}", message),
+        }
+    }
+
+    //Matches the swapchain to the window size and present policy. While the window is minimized the old
+    //swapchain is kept, and recreated on the first frame drawn after it's restored.
+    pub fn resize(&mut self) -> Result<(), RendererError> {
+        let swapchain = gen_swapchain(self.surface.clone(), self.queues.graphics.clone(), self.device.clone(),
+            self.present_policy, Some(&self.swapchain));
+        let (swapchain, images) = match swapchain {
+            Ok(res) => res,
+            Err(RendererError::Swapchain(SwapchainCreationError::UnsupportedDimensions)) => {
+                self.recreate_swapchain = true;
+                return Ok(());
+            },
+            Err(err) => return Err(err),
+        };
+
+        self.swapchain = swapchain;
+        self.framebuffers = gen_framebuffers(&images, self.render_pass.clone(), self.device.clone())?;
+        self.recreate_swapchain = false;
+        Ok(())
+    }
+
+    pub fn present_policy(&self) -> PresentPolicy {
+        self.present_policy
+    }
+
+    //Recreates the swapchain when the policy changes
+    pub fn set_present_policy(&mut self, policy: PresentPolicy) -> Result<(), RendererError> {
+        if policy == self.present_policy {
+            return Ok(());
+        }
+        self.present_policy = policy;
+        self.resize()
+    }
+
+    //Returns the id meshes refer to the material by
+    pub fn upload_material(&mut self, material: &Material) -> Result<usize, RendererError> {
+        let (set, futures) = gen_material_set(&self.queues, self.pipelines.triangles.clone(),
+            self.sampler.clone(), material, self.blank_texture.clone())?;
+        for future in futures {
+            let previous = mem::replace(&mut self.pending_uploads, Box::new(sync::now(self.device.clone())));
+            self.pending_uploads = Box::new(previous.join(future));
+        }
+        Ok(self.scene.add_material(set))
+    }
+
+    //Returns the id of the mesh, which is also the order meshes were uploaded in
+    pub fn upload_mesh(&mut self, mesh: &Mesh, material: usize) -> Result<usize, RendererError> {
+        let (buffers, future) = MeshBuffers::new(&self.queues, mesh)?;
+        let previous = mem::replace(&mut self.pending_uploads, Box::new(sync::now(self.device.clone())));
+        self.pending_uploads = Box::new(previous.join(future));
+        Ok(self.scene.add_mesh(buffers, material))
+    }
+
+    //Uploads every material and mesh of the model and returns the mesh ids. Meshes without a material,
+    //or with one missing from the library, are drawn with `fallback_material`. The model's lighting
+    //replaces the current one.
+    pub fn upload_model(&mut self, model: &Model, fallback_material: &Material) -> Result<Vec<usize>, RendererError> {
+        self.lighting = model.lighting.clone();
+        let materials = model.materials.iter().map(|material| self.upload_material(material))
+            .collect::<Result<Vec<_>, _>>()?;
+        let fallback = self.upload_material(fallback_material)?;
+        model.meshes.iter().zip(model.mesh_materials()).map(|(mesh, material)| {
+            self.upload_mesh(mesh, material.map_or(fallback, |m| materials[m]))
+        }).collect()
+    }
+
+    pub fn lighting(&self) -> &Lighting {
+        &self.lighting
+    }
+
+    //Only the first light::MAX_LIGHTS lights are drawn
+    pub fn set_lighting(&mut self, lighting: Lighting) {
+        if lighting.lights.len() > light::MAX_LIGHTS {
+            warn!("Only the first {} of {} lights are drawn", light::MAX_LIGHTS, lighting.lights.len());
+        }
+        self.lighting = lighting;
+    }
+
+    pub fn is_visible(&self, mesh: usize) -> bool {
+        self.scene.visible.get(mesh).cloned().unwrap_or(false)
+    }
+
+    pub fn set_visible(&mut self, mesh: usize, visible: bool) {
+        if let Some(v) = self.scene.visible.get_mut(mesh) {
+            *v = visible;
+        }
+    }
+
+    //Draws every visible mesh with `model_matrix` and presents the frame. Frames that can't be drawn
+    //because the window changed size are skipped.
+    pub fn draw_frame(&mut self, camera: &Camera, model_matrix: Matrix4<f32>) -> Result<(), RendererError> {
+        self.validation.check();
+        #[cfg(feature = "hot-reload")]
+        self.reload_shaders();
+        //The frame fences are cleaned up by waiting on them in order below. Cleaning up the last one here
+        //would also release the frames chained before it, before their own slots are waited on.
+        self.pending_uploads.cleanup_finished();
+
+        if self.recreate_swapchain {
+            self.resize()?;
+            if self.recreate_swapchain {
+                return Ok(());
+            }
+        }
+
+        //Blocks only when the CPU is a full ring of frames ahead of the GPU
+        let wait_start = Instant::now();
+        if let Some(fence) = self.frames[self.frame].fence.take() {
+            fence.wait(None)?;
+        }
+        let waited = wait_start.elapsed();
+
+        let (image_num, acquire_future) = match vulkano::swapchain::acquire_next_image(self.swapchain.clone(), None) {
+            Ok(res) => res,
+            Err(AcquireError::OutOfDate) => {
+                self.recreate_swapchain = true;
+                return Ok(());
+            }, 
+            Err(err) => return Err(err.into()),
+        }; 
+
+        {
+            let dimensions = self.swapchain.dimensions();
+            let aspect = dimensions[0] as f32 / dimensions[1] as f32;
+            let frame = &self.frames[self.frame];
+            *frame.uniforms.write()? = vertex_uniforms(model_matrix, camera.view(), camera.projection(aspect));
+            *frame.lights.write()? = light::uniforms(&self.lighting);
+        }
+
+        let builder = AutoCommandBufferBuilder::primary_one_time_submit(self.device.clone(), self.queues.graphics.family())
+            .map_err(|err| RendererError::Frame(Box::new(err)))?
+            .begin_render_pass(self.framebuffers[image_num].clone(), false,
+                vec!(CLEAR_COLOR.into(), 1f32.into()))?;
+        let command_buffer = draw_scene(builder, &self.pipelines, &dynamic_state(self.swapchain.dimensions()),
+            &self.scene, self.frames[self.frame].set.clone())?
+            .end_render_pass()?
+            .build()?;
+
+        //Chaining onto the previous frame, rather than waiting for it, lets vulkano see that the
+        //attachments shared between frames are already in use on the same queue
+        let uploads = mem::replace(&mut self.pending_uploads, Box::new(sync::now(self.device.clone())));
+        let previous = match self.previous_frame_end.take() {
+            Some(previous) => Box::new(previous.join(uploads)) as Box<GpuFuture>,
+            None => uploads,
+        };
+        let future = Box::new(previous.join(acquire_future)
+            .then_execute(self.queues.graphics.clone(), command_buffer)?
+            .then_swapchain_present(self.queues.graphics.clone(), self.swapchain.clone(), image_num)) as Box<GpuFuture>;
+
+        match future.then_signal_fence_and_flush() {
+            Ok(fence) => {
+                let fence = Arc::new(fence);
+                self.frames[self.frame].fence = Some(fence.clone());
+                self.previous_frame_end = Some(fence);
+                self.frame = (self.frame + 1) % self.frames.len();
+                self.stats.record(waited);
+            },
+            Err(FlushError::OutOfDate) => self.recreate_swapchain = true,
+            Err(err) => return Err(err.into()),
+        }
+        Ok(())
+    }
+}
+
+//The uniforms start out as identity matrices and the default lights, every frame overwrites them
+//before drawing
+fn gen_frames(device: Arc<Device>, pipeline: Arc<GraphicsPipelineAbstract + Send + Sync>, count: usize)
+    -> Result<Vec<Frame>, RendererError> {
+
+    (0..max(count, 1)).map(|_| {
+        let identity = Matrix4::<f32>::identity();
+        let uniforms = CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::uniform_buffer(),
+            vertex_uniforms(identity, identity, identity))?;
+        let lights = CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::uniform_buffer(),
+            light::uniforms(&Lighting::default()))?;
+        let set = Arc::new(PersistentDescriptorSet::start(pipeline.clone(), 0)
+            .add_buffer(uniforms.clone())?
+            .add_buffer(lights.clone())?
+            .build()?) as Arc<DescriptorSet + Send + Sync>;
+        Ok(Frame { uniforms, lights, set, fence: None })
+    }).collect()
+}
+
+//Normals are transformed by the inverse transpose of the model matrix, so they stay perpendicular to
+//the surface when the model is scaled unevenly
+fn vertex_uniforms(model: Matrix4<f32>, view: Matrix4<f32>, proj: Matrix4<f32>) -> vertex::ty::Data {
+    let normal_matrix = model.invert().unwrap_or_else(Matrix4::identity).transpose();
+    vertex::ty::Data {
+        model: model.into(),
+        view: view.into(),
+        proj: proj.into(),
+        normal_matrix: normal_matrix.into(),
+    }
+}
+
+fn init_vulkan(events_loop: &EventsLoop, options: &RendererOptions)
+    -> Result<(Arc<Device>, Queues, Arc<Surface<Window>>, Validation), RendererError> {
+        
+    let (instance, validation) = validation::create_instance(&vulkano_win::required_extensions(), &options.validation)?;
+
+    let surface = WindowBuilder::new()
+        .with_title(WINDOW_TITLE)
+        .with_decorations(true)
+        .build_vk_surface(events_loop, instance.clone())?;
+
+    let features = gpu::required_features(&options.sampler);
+    let physical_device = gpu::choose(&instance, Some(&surface), options.gpu.as_ref(), &features)
+        .map_err(RendererError::NoDevice)?;
+    info!("Using device {}: {}", physical_device.index(), physical_device.name());
+
+    let queue_family = gpu::queue_family(physical_device, Some(&surface)).ok_or(RendererError::NoQueue)?;
+    
+    let requests = gpu::QueueRequests::new(physical_device, queue_family);
+    let (device, queues) = Device::new(physical_device, &features, &gpu::required_extensions(Some(&surface)),
+        requests.families.iter().cloned())?;
+    let queues = requests.queues(queues).ok_or(RendererError::NoQueue)?;
+
+    Ok((device, queues, surface, validation))
+} 
+
+//Replaces `old_swapchain` when there is one
+fn gen_swapchain(surface: Arc<Surface<Window>>, queue: Arc<Queue>, device: Arc<Device>, policy: PresentPolicy,
+    old_swapchain: Option<&Arc<Swapchain<Window>>>)
+    -> Result<(Arc<Swapchain<Window>>, Vec<Arc<SwapchainImage<Window>>>), RendererError> {
+        
+    let window = surface.window();
+
+    let capabilities = surface.capabilities(device.physical_device())?;
+
+    let usage = capabilities.supported_usage_flags;
+
+    let alpha = capabilities.supported_composite_alpha.iter().next()
+        .ok_or(SwapchainCreationError::UnsupportedCompositeAlpha)?;
+    
+    let format = present::choose_format(&capabilities).ok_or(SwapchainCreationError::UnsupportedFormat)?;
+    
+    //TODO: Use more layers if necessary.
+    let layers = 1;
+
+    let dimensions = get_window_dimensions(&window)?;
+
+    let transform = capabilities.current_transform;
+
+    //Attempt to use triple buffering
+    let buffer_count = if let Some(limit) = capabilities.max_image_count {
+        min(3, limit)
+    } else { 
+        max(3, capabilities.min_image_count) 
+    };
+
+    let clip = true; //Clip parts of the buffer which aren't visible
+
+    let present_mode = present::choose_present_mode(&capabilities, policy);
+
+    info!("Swapchain: {}x{} {:?}, {:?} presentation ({}), {} images", dimensions[0], dimensions[1], format,
+        present_mode, policy, buffer_count);
+    Ok(Swapchain::new(device.clone(), surface.clone(), buffer_count, format, dimensions,
+        layers, usage, &queue, transform, alpha, present_mode, clip, old_swapchain)?)
+}
+
+//Color maps are sRGB encoded, data like bump maps should use a Unorm format instead.
+//The full mip chain is blitted on the GPU, or built on the CPU if the blits can't be recorded. Blits need a
+//graphics queue, so uploads on a dedicated transfer queue always build the chain on the CPU.
+fn load_texture(queue: Arc<Queue>, image: image::RgbaImage, format: Format) ->
+    Result<(Arc<ImmutableImage<Format>>, CommandBufferExecFuture<NowFuture, AutoCommandBuffer>), RendererError> {
+
+    let (width, height) = image.dimensions();
+    let levels = texture::mip_levels(width, height);
+    let usage = ImageUsage {
+        transfer_destination: true,
+        sampled: true,
+        ..ImageUsage::none()
+    };
+    let (texture, init) = ImmutableImage::uninitialized(queue.device().clone(),
+        Dimensions::Dim2d { width, height }, format, MipmapsCount::Specific(levels), usage,
+        ImageLayout::ShaderReadOnlyOptimal, queue.device().active_queue_families())?;
+    let init = Arc::new(init);
+
+    let blitted = if queue.family().supports_graphics() {
+        let builder = AutoCommandBufferBuilder::new(queue.device().clone(), queue.family())
+            .map_err(|err| RendererError::Upload(Box::new(err)))?;
+        blit_mipmaps(builder, queue.clone(), &image, format, init.clone(), levels)
+            .map_err(|err| warn!("Could not blit mipmaps, building them on the CPU instead: {}", err))
+            .ok()
+    } else {
+        None
+    };
+    let builder = match blitted {
+        Some(builder) => builder,
+        None => {
+            let builder = AutoCommandBufferBuilder::new(queue.device().clone(), queue.family())
+                .map_err(|err| RendererError::Upload(Box::new(err)))?;
+            copy_mipmaps(builder, queue.clone(), &image, format, init).map_err(RendererError::Upload)?
+        }
+    };
+
+    let command_buffer = builder.build().map_err(|err| RendererError::Upload(Box::new(err)))?;
+    let future = command_buffer.execute(queue).map_err(|err| RendererError::Upload(Box::new(err)))?;
+    Ok((texture, future))
+} 
+
+//vulkano tracks the layout of a whole image, not of its levels, so it can't blit from one level of an
+//image into another. Every level is blitted between scratch images instead, then copied into the texture.
+fn blit_mipmaps(builder: AutoCommandBufferBuilder, queue: Arc<Queue>, image: &image::RgbaImage, format: Format,
+    target: Arc<ImmutableImageInitialization<Format>>, levels: u32) -> Result<AutoCommandBufferBuilder, Box<Error>> {
+
+    let device = queue.device().clone();
+    let (width, height) = image.dimensions();
+    let usage = ImageUsage {
+        transfer_source: true,
+        transfer_destination: true,
+        ..ImageUsage::none()
+    };
+    let scratch = (0..levels).map(|level| {
+        let (w, h) = texture::mip_dimensions(width, height, level);
+        StorageImage::with_usage(device.clone(), Dimensions::Dim2d { width: w, height: h }, format, usage,
+            Some(queue.family()))
+    }).collect::<Result<Vec<_>, _>>()?;
+
+    let pixels = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::transfer_source(),
+        image.clone().into_raw().into_iter())?;
+    let mut builder = builder.copy_buffer_to_image(pixels, scratch[0].clone())?;
+
+    for level in 0..levels as usize {
+        let (w, h) = texture::mip_dimensions(width, height, level as u32);
+        if level > 0 {
+            let (pw, ph) = texture::mip_dimensions(width, height, level as u32 - 1);
+            builder = builder.blit_image(scratch[level - 1].clone(), [0, 0, 0], [pw as i32, ph as i32, 1], 0, 0,
+                scratch[level].clone(), [0, 0, 0], [w as i32, h as i32, 1], 0, 0, 1, Filter::Linear)?;
+        }
+        builder = builder.copy_image(scratch[level].clone(), [0, 0, 0], 0, 0,
+            target.clone(), [0, 0, 0], 0, level as u32, [w, h, 1], 1)?;
+    }
+
+    Ok(builder)
+}
+
+fn copy_mipmaps(builder: AutoCommandBufferBuilder, queue: Arc<Queue>, image: &image::RgbaImage, format: Format,
+    target: Arc<ImmutableImageInitialization<Format>>) -> Result<AutoCommandBufferBuilder, Box<Error>> {
+
+    let srgb = format == Format::R8G8B8A8Srgb;
+    let mut builder = builder;
+    for (level, mip) in texture::mip_chain(image, srgb).into_iter().enumerate() {
+        let (w, h) = mip.dimensions();
+        let pixels = CpuAccessibleBuffer::from_iter(queue.device().clone(), BufferUsage::transfer_source(),
+            mip.into_raw().into_iter())?;
+        builder = builder.copy_buffer_to_image_dimensions(pixels, target.clone(), [0, 0, 0], [w, h, 1],
+            0, 1, level as u32)?;
+    }
+
+    Ok(builder)
+}
+
+//Anisotropy is clamped to what the device supports instead of failing
+fn gen_sampler(device: Arc<Device>, options: &SamplerOptions) -> Result<Arc<Sampler>, SamplerCreationError> {
+    let anisotropy = if device.enabled_features().sampler_anisotropy {
+        options.anisotropy.min(device.physical_device().limits().max_sampler_anisotropy()).max(1.0)
+    } else {
+        1.0
+    };
+
+    Sampler::new(device, options.filter, options.filter, options.mipmap_mode(),
+        options.address_mode, options.address_mode, options.address_mode,
+        0.0, anisotropy, 0.0, 1000.0)
+}
+
+//Uploads run on the transfer queue, when that's a separate queue the graphics queue waits on a semaphore.
+//Images are created with concurrent sharing between the queue families, so they need no ownership transfer.
+fn upload_future<F: GpuFuture + 'static>(queues: &Queues, future: F) -> Box<GpuFuture> {
+    if queues.separate_transfer() {
+        Box::new(future.then_signal_semaphore())
+    } else {
+        Box::new(future)
+    }
+}
+
+//Set 1 of every pipeline: the material constants, the diffuse map and the bump map.
+//Texture files that can't be read only warn and fall back to the blank texture, failing to upload one is an error.
+fn gen_material_set(queues: &Queues, pipeline: Arc<GraphicsPipelineAbstract + Send + Sync>, sampler: Arc<Sampler>,
+    material: &mtl::Material, blank: Arc<ImmutableImage<Format>>)
+    -> Result<(Arc<DescriptorSet + Send + Sync>, Vec<Box<GpuFuture>>), RendererError> {
+
+    let mut futures = Vec::new();
+    let mut texture = |path: &Option<PathBuf>, format: Format| -> Result<_, RendererError> {
+        match path {
+            Some(path) => match texture::load_file(path) {
+                Ok(image) => {
+                    let (texture, future) = load_texture(queues.transfer.clone(), image, format)?;
+                    futures.push(upload_future(queues, future));
+                    Ok(texture)
+                },
+                Err(err) => {
+                    warn!("Could not load texture {} of material {}: {}", path.display(), material.name, err);
+                    Ok(blank.clone())
+                }
+            },
+            None => Ok(blank.clone()),
+        }
+    };
+    let diffuse_map = texture(&material.diffuse_map, Format::R8G8B8A8Srgb)?;
+    let bump_map = texture(&material.bump_map, Format::R8G8B8A8Unorm)?;
+
+    let [r, g, b] = material.diffuse;
+    let [sr, sg, sb] = material.specular;
+    let constants = CpuAccessibleBuffer::from_data(queues.graphics.device().clone(), BufferUsage::uniform_buffer(),
+        frag::ty::Material {
+            diffuse: [r, g, b, material.alpha],
+            specular: [sr, sg, sb, material.shininess],
+        })?;
+
+    let set = Arc::new(PersistentDescriptorSet::start(pipeline, 1)
+        .add_buffer(constants)?
+        .add_sampled_image(diffuse_map, sampler.clone())?
+        .add_sampled_image(bump_map, sampler)?
+        .build()?);
+
+    Ok((set, futures))
+}
+
+struct SceneBuffers {
+    meshes: Vec<MeshBuffers>,
+    //Toggled with the number keys
+    visible: Vec<bool>,
+    //Set 1 of every pipeline
+    materials: Vec<Arc<DescriptorSet + Send + Sync>>,
+    //(mesh, material) pairs sorted by material, so every material's batches are recorded back to back
+    draw_order: Vec<(usize, usize)>,
+}
+
+impl SceneBuffers {
+    fn new() -> Self {
+        SceneBuffers {
+            meshes: Vec::new(),
+            visible: Vec::new(),
+            materials: Vec::new(),
+            draw_order: Vec::new(),
+        }
+    }
+
+    fn add_material(&mut self, set: Arc<DescriptorSet + Send + Sync>) -> usize {
+        self.materials.push(set);
+        self.materials.len() - 1
+    }
+
+    fn add_mesh(&mut self, buffers: MeshBuffers, material: usize) -> usize {
+        let mesh = self.meshes.len();
+        self.meshes.push(buffers);
+        self.visible.push(true);
+        let position = self.draw_order.iter().position(|&(_, m)| m > material).unwrap_or(self.draw_order.len());
+        self.draw_order.insert(position, (mesh, material));
+        mesh
+    }
+}
+
+//Stands in for missing texture maps, white leaves the material colors and a flat bump map untouched
+fn gen_blank_texture(queues: &Queues) -> Result<(Arc<ImmutableImage<Format>>, Box<GpuFuture>), RendererError> {
+    let (texture, future) = load_texture(queues.transfer.clone(),
+        image::RgbaImage::from_pixel(1, 1, image::Rgba([255, 255, 255, 255])), Format::R8G8B8A8Unorm)?;
+    Ok((texture, upload_future(queues, future)))
+}
+
+//Renderer::upload_model for the headless path. The returned future finishes once every texture and buffer
+//is uploaded.
+fn upload_scene(queues: &Queues, scene: &Model, fallback_material: &Material,
+    pipeline: Arc<GraphicsPipelineAbstract + Send + Sync>, sampler: Arc<Sampler>)
+    -> Result<(SceneBuffers, Box<GpuFuture>), RendererError> {
+
+    let device = queues.graphics.device().clone();
+    let (blank_texture, blank_future) = gen_blank_texture(queues)?;
+    let mut upload_futures = vec![blank_future];
+    let mut buffers = SceneBuffers::new();
+
+    let materials = scene.materials.iter().chain(std::iter::once(fallback_material)).map(|material| {
+        let (set, futures) = gen_material_set(queues, pipeline.clone(), sampler.clone(),
+            material, blank_texture.clone())?;
+        upload_futures.extend(futures);
+        Ok(buffers.add_material(set))
+    }).collect::<Result<Vec<_>, RendererError>>()?;
+    let fallback = materials[materials.len() - 1];
+    for (mesh, material) in scene.meshes.iter().zip(scene.mesh_materials()) {
+        let (mesh_buffers, future) = MeshBuffers::new(queues, mesh)?;
+        upload_futures.push(future);
+        buffers.add_mesh(mesh_buffers, material.map_or(fallback, |m| materials[m]));
+    }
+
+    let future = upload_futures.into_iter()
+        .fold(Box::new(sync::now(device)) as Box<GpuFuture>, |joined, future| Box::new(joined.join(future)));
+    Ok((buffers, future))
+}
+
+//Records every visible mesh, `uniforms` is bound as set 0 (camera and lights) next to each material
+fn draw_scene<U>(mut builder: AutoCommandBufferBuilder, pipelines: &Pipelines, dynamic_state: &DynamicState,
+    scene: &SceneBuffers, uniforms: U) -> Result<AutoCommandBufferBuilder, DrawIndexedError>
+    where U: DescriptorSet + Clone + Send + Sync + 'static {
+
+    for &(mesh, material) in scene.draw_order.iter().filter(|&&(mesh, _)| scene.visible[mesh]) {
+        let buffers = &scene.meshes[mesh];
+        let sets = (uniforms.clone(), scene.materials[material].clone());
+        let batches = [
+            (&pipelines.triangles, &buffers.triangles),
+            (&pipelines.lines, &buffers.lines),
+            (&pipelines.points, &buffers.points),
+        ];
+        for (pipeline, index_buffer) in batches.iter() {
+            builder = match index_buffer {
+                Some(IndexBuffer::U16(indices)) => draw_batch(builder, (*pipeline).clone(), dynamic_state,
+                    buffers.vertices.clone(), indices.clone(), sets.clone())?,
+                Some(IndexBuffer::U32(indices)) => draw_batch(builder, (*pipeline).clone(), dynamic_state,
+                    buffers.vertices.clone(), indices.clone(), sets.clone())?,
+                None => builder,
+            };
+        }
+    }
+
+    Ok(builder)
+}
+
+fn gen_render_pass(device: Arc<Device>, color_format: Format)
+    -> Result<Arc<RenderPassAbstract + Send + Sync>, RenderPassCreationError> {
+
+    Ok(Arc::new(vulkano::single_pass_renderpass!(
+        device,
+        attachments: {
+            color: {
+                load: Clear,
+                store: Store,
+                format: color_format,
+                samples: 1,
+            },
+            depth: {
+               load: Clear,
+               store: DontCare,
+               format: Format::D16Unorm,
+               samples: 1,
+            } 
+        },
+        pass: {
+            color: [color],
+            depth_stencil: {depth}
+        }
+    )?))
+}
+
+//Writes the data to a staging buffer and copies it into device local memory on the transfer queue.
+//The copy's future is added to `futures`.
+fn upload_buffer<T>(queues: &Queues, usage: BufferUsage, data: &[T], futures: &mut Vec<Box<GpuFuture>>)
+    -> Result<Arc<ImmutableBuffer<[T]>>, DeviceMemoryAllocError>
+    where T: Clone + Send + Sync + 'static {
+
+    let (buffer, future) = ImmutableBuffer::from_iter(data.iter().cloned(), usage, queues.transfer.clone())?;
+    futures.push(upload_future(queues, future));
+    Ok(buffer)
+}
+
+fn draw_batch<I, S>(builder: AutoCommandBufferBuilder, pipeline: Arc<GraphicsPipelineAbstract + Send + Sync>,
+    dynamic_state: &DynamicState, vertices: Arc<ImmutableBuffer<[ModelVertex]>>, indices: Arc<ImmutableBuffer<[I]>>, sets: S)
+    -> Result<AutoCommandBufferBuilder, DrawIndexedError>
+    where I: Index + Send + Sync + 'static, S: DescriptorSetsCollection {
+
+    builder.draw_indexed(pipeline, dynamic_state, vec!(vertices), indices, sets, ())
+}
+
+//Covers the whole target, the pipelines leave viewport and scissor dynamic
+fn dynamic_state(dimensions: [u32; 2]) -> DynamicState {
+    DynamicState {
+        viewports: Some(vec![Viewport {
+            origin: [0.0, 0.0],
+            dimensions: [dimensions[0] as f32, dimensions[1] as f32],
+            depth_range: 0.0..1.0,
+        }]),
+        scissors: Some(vec![Scissor { origin: [0, 0], dimensions }]),
+        ..DynamicState::none()
+    }
+}
+
+//Along with the depth buffer they share, the only thing that depends on the window size
+fn gen_framebuffers(
+    images: &[Arc<SwapchainImage<Window>>],
+    render_pass: Arc<RenderPassAbstract + Send + Sync>,
+    device: Arc<Device>,
+    ) -> Result<Vec<Arc<FramebufferAbstract + Send + Sync>>, RendererError> {
+
+    let dimensions = images[0].dimensions();
+
+    let depth_buffer = AttachmentImage::transient(device.clone(), dimensions, Format::D16Unorm)?;
+
+    let framebuffers = images.iter().map(|image| {
+        Ok(Arc::new(
+            Framebuffer::start(render_pass.clone())
+                .add(image.clone())?
+                .add(depth_buffer.clone())?
+                .build()?
+        ) as Arc<FramebufferAbstract + Send + Sync>)
+    }).collect::<Result<Vec<_>, FramebufferCreationError>>()?;
+
+    Ok(framebuffers)
+}
+
+fn gen_pipelines(
+    render_pass: Arc<RenderPassAbstract + Send + Sync>,
+    device: Arc<Device>,
+    shaders: &Shaders,
+    ) -> Result<Pipelines, RendererError> {
+
+    let subpass = Subpass::from(render_pass, 0).ok_or(RendererError::NoSubpass)?;
+    let pipeline = |topology: PrimitiveTopology| GraphicsPipeline::start()
+        .vertex_input(SingleBufferDefinition::<ModelVertex>::new())
+        .vertex_shader(shaders.vertex_entry_point(), ())
+        .primitive_topology(topology)
+        .viewports_scissors_dynamic(1)
+        .fragment_shader(shaders.fragment_entry_point(), ())
+        .blend_alpha_blending()
+        .depth_stencil_simple_depth()
+        .render_pass(subpass.clone())
+        .build(device.clone())
+        .map(|pipeline| Arc::new(pipeline) as Arc<GraphicsPipelineAbstract + Send + Sync>);
+
+    Ok(Pipelines {
+        triangles: pipeline(PrimitiveTopology::TriangleList)?,
+        lines: pipeline(PrimitiveTopology::LineList)?,
+        points: pipeline(PrimitiveTopology::PointList)?,
+    })
+}
+
+fn get_window_dimensions(window: &Window) -> Result<[u32;2], SwapchainCreationError> {
+    
+    //NOTE: We could set this to capabilities.current_extent.unwrap_or(DEFAULT..)
+    //But since either way we want the initial dimensions to be the window dimensions
+    //we just get the physical dimensions this way
+    if let Some(dimensions) = window.get_inner_size() {
+        let dimensions: (u32, u32) = dimensions.to_physical(window.get_hidpi_factor()).into();
+        Ok([dimensions.0, dimensions.1])
+    } else {
+       Err(SwapchainCreationError::SurfaceLost) 
+    }  
+}  
+
+mod vertex {
+    vulkano_shaders::shader!{
+        ty: "vertex",
+        path: "src/vertex.glsl"
+    }
+}
+
+mod frag {
+    vulkano_shaders::shader!{
+        ty: "fragment",
+        path: "src/frag.glsl"
+    }
+}
+
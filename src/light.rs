@@ -0,0 +1,330 @@
+use super::frag;
+use super::load::{statements, LoadError};
+use std::cmp::min;
+use std::fs;
+use std::path::Path;
+use std::vec::IntoIter;
+
+//Size of the light array in frag.glsl
+pub const MAX_LIGHTS: usize = 8;
+
+//How frag.glsl tells the kinds apart, stored in the w of the position
+const DIRECTIONAL: f32 = 0.0;
+const POINT: f32 = 1.0;
+const SPOT: f32 = 2.0;
+
+//How point and spot lights fade with the distance d, 1 / (constant + linear * d + quadratic * d²)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Attenuation {
+    pub constant: f32,
+    pub linear: f32,
+    pub quadratic: f32,
+}
+
+impl Default for Attenuation {
+    //No falloff, so how far a light reaches doesn't depend on the scale of the model
+    fn default() -> Self {
+        Attenuation { constant: 1.0, linear: 0.0, quadratic: 0.0 }
+    }
+}
+
+//Positions and directions are in world space, directions point away from the light
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Light {
+    //Infinitely far away, like the sun
+    Directional {
+        direction: [f32; 3],
+        color: [f32; 3],
+        intensity: f32,
+    },
+    Point {
+        position: [f32; 3],
+        color: [f32; 3],
+        intensity: f32,
+        attenuation: Attenuation,
+    },
+    //Full intensity within inner_angle of the direction, fading out towards outer_angle, both in degrees
+    Spot {
+        position: [f32; 3],
+        direction: [f32; 3],
+        color: [f32; 3],
+        intensity: f32,
+        attenuation: Attenuation,
+        inner_angle: f32,
+        outer_angle: f32,
+    },
+}
+
+impl Light {
+    fn uniform(&self) -> frag::ty::Light {
+        match *self {
+            Light::Directional { direction, color, intensity } => gpu_light(DIRECTIONAL, [0.0; 3], direction,
+                color, intensity, Attenuation::default(), [0.0, 0.0]),
+            Light::Point { position, color, intensity, attenuation } => gpu_light(POINT, position, [0.0, 0.0, -1.0],
+                color, intensity, attenuation, [0.0, 0.0]),
+            Light::Spot { position, direction, color, intensity, attenuation, inner_angle, outer_angle } =>
+                gpu_light(SPOT, position, direction, color, intensity, attenuation, [inner_angle, outer_angle]),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lighting {
+    //Added to every surface, so the sides facing away from all lights aren't black
+    pub ambient: [f32; 3],
+    //Only the first MAX_LIGHTS are drawn
+    pub lights: Vec<Light>,
+}
+
+impl Default for Lighting {
+    //A white light from the side the default camera looks from, matching the fixed light the shader
+    //used before lights were configurable
+    fn default() -> Self {
+        Lighting {
+            ambient: [0.6, 0.6, 0.6],
+            lights: vec![Light::Directional { direction: [0.0, 0.0, -1.0], color: [1.0, 1.0, 1.0], intensity: 0.4 }],
+        }
+    }
+}
+
+//The Lights uniform block of frag.glsl
+pub(crate) fn uniforms(lighting: &Lighting) -> frag::ty::Lights {
+    let unused = gpu_light(DIRECTIONAL, [0.0; 3], [0.0, 0.0, -1.0], [0.0; 3], 0.0, Attenuation::default(), [0.0, 0.0]);
+    let mut lights = [unused; MAX_LIGHTS];
+    for (slot, light) in lights.iter_mut().zip(lighting.lights.iter()) {
+        *slot = light.uniform();
+    }
+    let [r, g, b] = lighting.ambient;
+    frag::ty::Lights {
+        ambient: [r, g, b, 0.0],
+        lights,
+        count: min(lighting.lights.len(), MAX_LIGHTS) as u32,
+    }
+}
+
+fn gpu_light(kind: f32, position: [f32; 3], direction: [f32; 3], color: [f32; 3], intensity: f32,
+    attenuation: Attenuation, cone_angles: [f32; 2]) -> frag::ty::Light {
+
+    let [x, y, z] = position;
+    let [dx, dy, dz] = direction;
+    let [r, g, b] = color;
+    let [inner, outer] = cone_angles;
+    frag::ty::Light {
+        position: [x, y, z, kind],
+        direction: [dx, dy, dz, 0.0],
+        color: [r * intensity, g * intensity, b * intensity, 0.0],
+        attenuation: [attenuation.constant, attenuation.linear, attenuation.quadratic, 0.0],
+        cone: [inner.to_radians().cos(), outer.to_radians().cos(), 0.0, 0.0],
+    }
+}
+
+pub fn load_lighting(path: &Path) -> Result<Lighting, LoadError> {
+    let contents = fs::read_to_string(path)?;
+    parse_lighting(&contents)
+}
+
+//One light per line, followed by its properties in any order. `#` starts a comment.
+//
+//    ambient 0.1 0.1 0.1
+//    directional direction 0 -1 0 color 1 1 1 intensity 0.5
+//    point position 0 50 100 color 1 0.8 0.6 intensity 2 attenuation 1 0 0.0001
+//    spot position 0 200 0 direction 0 -1 0 intensity 3 angles 15 25
+//
+//Missing properties give a white light of intensity 1 without attenuation, pointing down -z with
+//angles of 20 and 30 degrees. Without an ambient line there is no ambient light.
+pub fn parse_lighting(contents: &str) -> Result<Lighting, LoadError> {
+    let mut lighting = Lighting { ambient: [0.0; 3], lights: Vec::new() };
+
+    for (line_number, keyword, args) in statements(contents) {
+        let parse_error = |message: String| LoadError::Parse { line: line_number, message };
+        let mut tokens = args.into_iter();
+
+        let properties: &[&str] = match keyword {
+            "ambient" => {
+                lighting.ambient = vector(&mut tokens, keyword).map_err(parse_error)?;
+                if let Some(extra) = tokens.next() {
+                    return Err(parse_error(format!("unexpected `{}` after the ambient color", extra)));
+                }
+                continue;
+            },
+            "directional" => &["direction", "color", "intensity"],
+            "point" => &["position", "color", "intensity", "attenuation"],
+            "spot" => &["position", "direction", "color", "intensity", "attenuation", "angles"],
+            _ => return Err(parse_error(format!("unknown light `{}`, expected ambient, directional, point or spot", keyword))),
+        };
+        if lighting.lights.len() == MAX_LIGHTS {
+            return Err(parse_error(format!("at most {} lights are supported", MAX_LIGHTS)));
+        }
+
+        let mut position = [0.0; 3];
+        let mut direction = [0.0, 0.0, -1.0];
+        let mut color = [1.0; 3];
+        let mut intensity = 1.0;
+        let mut attenuation = Attenuation::default();
+        let mut angles = [20.0, 30.0];
+        while let Some(property) = tokens.next() {
+            if !properties.contains(&property) {
+                return Err(parse_error(format!("{} lights have no `{}`, expected one of {}", keyword, property,
+                    properties.join(", "))));
+            }
+            match property {
+                "position" => position = vector(&mut tokens, property).map_err(parse_error)?,
+                "direction" => direction = vector(&mut tokens, property).map_err(parse_error)?,
+                "color" => color = vector(&mut tokens, property).map_err(parse_error)?,
+                "intensity" => intensity = values(&mut tokens, 1, property).map_err(parse_error)?[0],
+                "attenuation" => {
+                    let [constant, linear, quadratic] = vector(&mut tokens, property).map_err(parse_error)?;
+                    attenuation = Attenuation { constant, linear, quadratic };
+                },
+                _ => {
+                    let values = values(&mut tokens, 2, property).map_err(parse_error)?;
+                    angles = [values[0], values[1]];
+                },
+            }
+        }
+
+        if direction == [0.0; 3] {
+            return Err(parse_error("the direction can't be zero".to_string()));
+        }
+        //The shader fades between the two with smoothstep, which is undefined when they are equal
+        if !(0.0 <= angles[0] && angles[0] < angles[1] && angles[1] < 180.0) {
+            return Err(parse_error("spot angles have to satisfy 0 <= inner < outer < 180".to_string()));
+        }
+        lighting.lights.push(match keyword {
+            "directional" => Light::Directional { direction, color, intensity },
+            "point" => Light::Point { position, color, intensity, attenuation },
+            _ => Light::Spot {
+                position,
+                direction,
+                color,
+                intensity,
+                attenuation,
+                inner_angle: angles[0],
+                outer_angle: angles[1],
+            },
+        });
+    }
+
+    Ok(lighting)
+}
+
+fn values(tokens: &mut IntoIter<&str>, count: usize, property: &str) -> Result<Vec<f32>, String> {
+    let values = tokens.by_ref().take(count).map(|token| token.parse::<f32>()).collect::<Result<Vec<_>, _>>();
+    match values {
+        Ok(values) if values.len() == count => Ok(values),
+        _ if count == 1 => Err(format!("`{}` expects a number", property)),
+        _ => Err(format!("`{}` expects {} numbers", property, count)),
+    }
+}
+
+fn vector(tokens: &mut IntoIter<&str>, property: &str) -> Result<[f32; 3], String> {
+    let values = values(tokens, 3, property)?;
+    Ok([values[0], values[1], values[2]])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn light(line: &str) -> Light {
+        let mut lighting = parse_lighting(line).unwrap();
+        assert_eq!(lighting.lights.len(), 1);
+        lighting.lights.remove(0)
+    }
+
+    fn error(contents: &str) -> (usize, String) {
+        match parse_lighting(contents) {
+            Err(LoadError::Parse { line, message }) => (line, message),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn each_kind_reads_its_properties() {
+        assert_eq!(light("directional direction 0 -1 0 color 1 0.5 0 intensity 0.5"),
+            Light::Directional { direction: [0.0, -1.0, 0.0], color: [1.0, 0.5, 0.0], intensity: 0.5 });
+        assert_eq!(light("point intensity 2 position 1 2 3 attenuation 1 0 0.25"), Light::Point {
+            position: [1.0, 2.0, 3.0],
+            color: [1.0; 3],
+            intensity: 2.0,
+            attenuation: Attenuation { constant: 1.0, linear: 0.0, quadratic: 0.25 },
+        });
+        assert_eq!(light("spot position 0 5 0 direction 0 -1 0 angles 10 20"), Light::Spot {
+            position: [0.0, 5.0, 0.0],
+            direction: [0.0, -1.0, 0.0],
+            color: [1.0; 3],
+            intensity: 1.0,
+            attenuation: Attenuation::default(),
+            inner_angle: 10.0,
+            outer_angle: 20.0,
+        });
+    }
+
+    #[test]
+    fn missing_properties_use_the_defaults() {
+        assert_eq!(light("spot"), Light::Spot {
+            position: [0.0; 3],
+            direction: [0.0, 0.0, -1.0],
+            color: [1.0; 3],
+            intensity: 1.0,
+            attenuation: Attenuation::default(),
+            inner_angle: 20.0,
+            outer_angle: 30.0,
+        });
+    }
+
+    #[test]
+    fn ambient_is_a_color_of_its_own() {
+        let lighting = parse_lighting("# scene\nambient 0.1 0.2 0.3\n").unwrap();
+        assert_eq!(lighting, Lighting { ambient: [0.1, 0.2, 0.3], lights: Vec::new() });
+        assert_eq!(parse_lighting("point").unwrap().ambient, [0.0; 3]);
+        assert_eq!(error("\nambient 1 1 1 1").0, 2);
+        assert_eq!(error("ambient 1 1").0, 1);
+    }
+
+    #[test]
+    fn unknown_kinds_and_properties_are_errors() {
+        assert_eq!(error("sun").1, "unknown light `sun`, expected ambient, directional, point or spot");
+        assert_eq!(error("point position 0 0 0\npoint direction 0 0 1"),
+            (2, "point lights have no `direction`, expected one of position, color, intensity, attenuation".to_string()));
+        assert_eq!(error("directional intensity bright").1, "`intensity` expects a number");
+        assert_eq!(error("point position 1 2").1, "`position` expects 3 numbers");
+    }
+
+    #[test]
+    fn direction_cant_be_zero() {
+        assert_eq!(error("directional direction 0 0 0").1, "the direction can't be zero");
+        assert_eq!(error("spot direction 0 0 0").0, 1);
+    }
+
+    #[test]
+    fn spot_angles_are_ordered() {
+        let message = "spot angles have to satisfy 0 <= inner < outer < 180";
+        assert_eq!(error("spot angles 30 10").1, message);
+        assert_eq!(error("spot angles 15 15").1, message);
+        assert_eq!(error("spot angles -5 10").1, message);
+        assert_eq!(error("spot angles 10 180").1, message);
+        match light("spot angles 0 15") {
+            Light::Spot { inner_angle, outer_angle, .. } => assert_eq!((inner_angle, outer_angle), (0.0, 15.0)),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn at_most_max_lights() {
+        assert_eq!(parse_lighting(&"point\n".repeat(MAX_LIGHTS)).unwrap().lights.len(), MAX_LIGHTS);
+        assert_eq!(error(&"point\n".repeat(MAX_LIGHTS + 1)).0, MAX_LIGHTS + 1);
+    }
+
+    #[test]
+    fn uniforms_fill_the_used_slots() {
+        let lighting = parse_lighting("ambient 0.5 0.5 0.5\npoint position 1 2 3\nspot angles 0 60").unwrap();
+        let uniforms = uniforms(&lighting);
+        assert_eq!(uniforms.count, 2);
+        assert_eq!(uniforms.ambient, [0.5, 0.5, 0.5, 0.0]);
+        assert_eq!(uniforms.lights[0].position, [1.0, 2.0, 3.0, POINT]);
+        assert_eq!(uniforms.lights[1].position[3], SPOT);
+        assert_eq!(uniforms.lights[1].cone[0], 1.0);
+        assert!((uniforms.lights[1].cone[1] - 0.5).abs() < 1e-6);
+    }
+}
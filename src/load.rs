@@ -0,0 +1,71 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+//Errors from reading the text formats: OBJ models, MTL material libraries and lights files
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    //Line numbers are one based and refer to the original file
+    Parse { line: usize, message: String },
+    BadIndex { line: usize, element: &'static str, index: isize, count: usize },
+    UnsupportedPrimitive { line: usize, statement: String },
+    TooManyVertices { mesh: String },
+    NoGeometry,
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Io(err) => write!(f, "{}", err),
+            LoadError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+            LoadError::BadIndex { line, element, index, count } =>
+                write!(f, "line {}: {} index {} is out of range, only {} defined so far", line, element, index, count),
+            LoadError::UnsupportedPrimitive { line, statement } =>
+                write!(f, "line {}: `{}` statements are not supported", line, statement),
+            LoadError::TooManyVertices { mesh } =>
+                write!(f, "{} has more vertices than 32 bit indices can address", mesh),
+            LoadError::NoGeometry => write!(f, "file contains no faces, lines or points"),
+        }
+    }
+}
+
+impl Error for LoadError {
+    fn source(&self) -> Option<&(Error + 'static)> {
+        match self {
+            LoadError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for LoadError {
+    fn from(err: io::Error) -> Self {
+        LoadError::Io(err)
+    }
+}
+
+//The statements of an OBJ, MTL or lights file as one based line number, keyword and arguments. `#` starts a
+//comment, lines without a statement are skipped.
+pub(crate) fn statements(contents: &str) -> impl Iterator<Item = (usize, &str, Vec<&str>)> {
+    contents.lines().enumerate().filter_map(|(number, line)| {
+        let mut tokens = line.split('#').next().unwrap_or("").split_whitespace();
+        tokens.next().map(|keyword| (number + 1, keyword, tokens.collect()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn statements_skip_comments_and_blank_lines() {
+        let contents = "# header\n\nv 1 2 3 # trailing\n   \nusemtl  red\tmetal\n#\nf";
+        let statements = statements(contents).collect::<Vec<_>>();
+        assert_eq!(statements, vec![
+            (3, "v", vec!["1", "2", "3"]),
+            (5, "usemtl", vec!["red", "metal"]),
+            (7, "f", vec![]),
+        ]);
+    }
+}
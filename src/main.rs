@@ -1,369 +1,169 @@
-#[macro_use]
-extern crate vulkano;
-extern crate vulkano_shaders;
+extern crate renderervk;
 extern crate winit;
-extern crate vulkano_win;
-extern crate image;
+extern crate cgmath;
+extern crate env_logger;
 
-use std::cmp::{min, max};
-use std::vec::Vec;
-use std::time::Instant;
-use std::sync::Arc;
-use std::error::Error;
-use winit::{Event, WindowEvent, WindowBuilder, EventsLoop, Window};
-use image::ImageFormat;
-use vulkano::instance::{PhysicalDevice, Instance};
-use vulkano::sampler::{Sampler, Filter, MipmapMode, SamplerAddressMode};
-use vulkano::image::{ImageCreationError, immutable::ImmutableImage, Dimensions};
-use vulkano_win::VkSurfaceBuild;
-use vulkano::device::{Device, DeviceExtensions, Queue, QueuesIter};
-use vulkano::swapchain::{AcquireError, Surface, Swapchain, SwapchainCreationError, PresentMode};
-use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
-use vulkano::image::{AttachmentImage, swapchain::SwapchainImage};
-use vulkano::buffer::{CpuBufferPool, BufferUsage, CpuAccessibleBuffer};
-use vulkano::pipeline::{GraphicsPipelineAbstract, viewport::Viewport, vertex::TwoBuffersDefinition, GraphicsPipeline};
-use vulkano::framebuffer::{Framebuffer, FramebufferAbstract, RenderPassAbstract, Subpass};
-use vulkano::format::{Format, ClearValue};
-use vulkano::command_buffer::{CommandBufferExecFuture, AutoCommandBuffer, AutoCommandBufferBuilder, DynamicState};
-use vulkano::sync;
-use vulkano::sync::{NowFuture, FlushError, GpuFuture};
+use std::time::{Duration, Instant};
+use winit::{Event, WindowEvent, EventsLoop};
+use cgmath::{Matrix4, SquareMatrix};
+use renderervk::{camera, golden, gpu, headless, input, light, mesh, mtl, Renderer, WINDOW_TITLE};
 
-mod objload;
-mod teapot;
-
-#[derive(Clone, Debug)]
-pub struct Vertex {
-    position: (f32, f32, f32),
-} vulkano::impl_vertex!(Vertex, position);
-
-#[derive(Clone, Debug)]
-pub struct Normal {
-    normal: (f32, f32, f32),
-} vulkano::impl_vertex!(Normal, normal);
-
-#[derive(Clone, Debug)]
-pub struct TexVert {
-    position2D: (f32, f32),
-} vulkano::impl_vertex!(TexVert, position2D); 
-
-pub type IndexType = u16;
-#[derive(Clone, Debug)]
-pub struct Indices {
-    v: Vec<IndexType>,
-    vn: Vec<IndexType>,
-    vt: Vec<IndexType>,
-}  
+mod cli;
 
 fn main() {
 
-    let (device, mut queues, surface, mut events_loop) = init_vulkan().expect("Intialization error");
-    let window = surface.window();
-
-    //TODO: Use multiple queues, and more efficiently.
-    let queue = queues.next().expect("Could not retrieve queue from queues");
-
-    let (mut swapchain, images) = gen_swapchain(surface.clone(), queue.clone(), device.clone())
-        .expect("Could not create swapchain");
+    //Validation messages and other diagnostics, RUST_LOG=info shows more
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
 
-    //let (vertices, tex_verts, normals, indices) = objload::load_model(include_str!("res/chalet.obj"))
-    //    .expect("Could not load model");
-
-    let vertices = teapot::VERTICES;
-    let normals = teapot::NORMALS;
-    let indices = teapot::INDICES;
+    let options = match cli::Options::from_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+    if options.help {
+        println!("{}", cli::USAGE);
+        return;
+    }
+    if options.list_gpus {
+        match gpu::list_gpus() {
+            Ok(ref devices) if devices.is_empty() => println!("No devices found"),
+            Ok(devices) => println!("{}", devices.join("\n\n")),
+            Err(err) => {
+                eprintln!("Could not create a Vulkan instance: {}", err);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
 
-    let vertex_buffer = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(), 
-        vertices.iter().cloned()).expect("Could not create vertex buffer");
-    let normals_buffer = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(), 
-        normals.iter().cloned()).expect("Could not create vertex buffer");
-    let v_index_buffer = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(),
-        indices.iter().cloned()).expect("Could not create v_index buffer");
-    //let vn_index_buffer = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(),
-    //    indices.vn.iter().cloned()).expect("Could not create vt_index buffer");
+    if let Some(ref directory) = options.golden {
+        let tolerance = options.tolerance.unwrap_or(golden::DEFAULT_TOLERANCE);
+        match golden::run(directory, options.bless, tolerance, options.renderer.gpu.as_ref(),
+            &options.renderer.validation) {
+            Ok(true) => return,
+            Ok(false) => std::process::exit(1),
+            Err(err) => {
+                eprintln!("Could not run golden image tests: {}", err);
+                std::process::exit(1);
+            }
+        }
+    }
 
-    //Ring buffer that contains sub-buffers which are freed upon being dropped (cleanup_finished())
-    //let fragment_color_buffer = CpuBufferPool::<frag::ty::ColorData>::new(device.clone(), BufferUsage::all());
+    let mut scene = match options.model {
+        Some(ref path) => mesh::load_obj(path).unwrap_or_else(|err| {
+            eprintln!("Could not load model {}: {}", path.display(), err);
+            std::process::exit(1);
+        }),
+        None => mesh::Model::teapot(),
+    };
+    if let Some(ref path) = options.lights {
+        scene.lighting = light::load_lighting(path).unwrap_or_else(|err| {
+            eprintln!("Could not load lights {}: {}", path.display(), err);
+            std::process::exit(1);
+        });
+    }
+    for (i, mesh) in scene.meshes.iter().enumerate() {
+        println!("Mesh {}: {} ({})", i + 1, mesh.name, mesh.material.as_ref().map_or("no material", |m| m.as_str()));
+    }
+    let bounds = scene.bounds().unwrap_or_else(|| {
+        eprintln!("Cannot frame an empty model");
+        std::process::exit(1);
+    });
+
+    //--texture replaces the diffuse map of every material. Meshes without a material get it untinted
+    //instead of the default red.
+    let mut fallback_material = mtl::Material::default();
+    if let Some(ref path) = options.texture {
+        if let Err(err) = std::fs::metadata(path) {
+            eprintln!("Could not open texture {}: {}", path.display(), err);
+            std::process::exit(1);
+        }
+        for material in scene.materials.iter_mut() {
+            material.diffuse_map = Some(path.clone());
+        }
+        fallback_material.diffuse = [1.0, 1.0, 1.0];
+        fallback_material.diffuse_map = Some(path.clone());
+    }
 
-    let vs = vertex::Shader::load(device.clone()).expect("Could not load vertex shader");
-    let fs =   frag::Shader::load(device.clone()).expect("Could not load fragment shader");
+    if let Some(ref output) = options.headless {
+        let dimensions = options.size.unwrap_or(headless::DEFAULT_SIZE);
+        if let Err(err) = headless::render_to_png(&scene, &fallback_material, &options.renderer, dimensions, output) {
+            eprintln!("Could not render {}: {}", output.display(), err);
+            std::process::exit(1);
+        }
+        println!("Rendered {}", output.display());
+        return;
+    }
 
+    let mut events_loop = EventsLoop::new();
+    let mut renderer = Renderer::new(&events_loop, &options.renderer).unwrap_or_else(|err| {
+        eprintln!("Could not start the renderer: {}", err);
+        std::process::exit(1);
+    });
+    let meshes = renderer.upload_model(&scene, &fallback_material).unwrap_or_else(|err| {
+        eprintln!("Could not upload the model: {}", err);
+        std::process::exit(1);
+    });
 
-    let render_pass = Arc::new(vulkano::single_pass_renderpass!(
-        device.clone(),
-        attachments: {
-            color: {
-                load: Clear,
-                store: Store,
-                format: swapchain.format(),
-                samples: 1,
-            },
-            depth: {
-               load: Clear,
-               store: DontCare,
-               format: Format::D16Unorm,
-               samples: 1,
-            } 
-        },
-        pass: {
-            color: [color],
-            depth_stencil: {depth}
-        }
-    ).expect("Could not create renderpass"));
-    
-    //let (texture, texture_future) = load_texture(queue.clone(), include_bytes!("res/texture.png"))
-    //    .expect("Error loading texture");
-    //let sampler = Sampler::new(device.clone(), Filter::Linear, Filter::Linear,
-    //    MipmapMode::Nearest, SamplerAddressMode::Repeat, SamplerAddressMode::Repeat,
-    //    SamplerAddressMode::Repeat, 0.0, 1.0, 0.0, 0.0).expect("Could not create sampler");
-    //let texture_set = Arc::new(PersistentDescriptorSet::start(pipeline.clone(), 1)
-    //    .add_sampled_image(texture.clone(), sampler.clone())
-    //    .expect("Could not add sampled image")
-    //    .build().unwrap());
-   
+    let mut controls = input::Controls::orbiting(bounds);
+    let mut camera = camera::Camera::perspective(controls.eye(), bounds.center());
+    let model_matrix = Matrix4::<f32>::identity();
 
-    let (mut pipeline, mut framebuffers) = gen_framebuffers_from_window_size(
-        &images, render_pass.clone(), device.clone(), &vs, &fs);
-    let mut recreate_swapchain = false;
-    //let mut previous_frame_end = Box::new(texture_future) as Box<GpuFuture>;
-    let mut previous_frame_end = Box::new(sync::now(device.clone())) as Box<GpuFuture>;
     let mut done = false;
+    let mut last_frame = Instant::now();
+    let mut last_stats = last_frame;
 
     loop {
-        previous_frame_end.cleanup_finished();
-        if recreate_swapchain {
-           
-            let dimensions = get_window_dimensions(&window).expect("Could not get new window dimensions");
-            let (new_swapchain, new_images) = match swapchain.recreate_with_dimension(dimensions) {
-                Ok(res) => res,
-                Err(SwapchainCreationError::UnsupportedDimensions) => continue,
-                Err(err) => panic!("{:?}", err)
-            }; 
-
-            swapchain = new_swapchain;
-            let (new_pipeline, new_framebuffers) = gen_framebuffers_from_window_size(&new_images, 
-                render_pass.clone(), device.clone(), &vs, &fs);
-            
-            pipeline = new_pipeline;
-            framebuffers = new_framebuffers;
-
-            recreate_swapchain = false;
-        } 
-
-        let (image_num, acquire_future) = match vulkano::swapchain::acquire_next_image(swapchain.clone(), None) {
-            Ok(res) => res,
-            Err(AcquireError::OutOfDate) => {
-                recreate_swapchain = true;
-                continue;
-            }, 
-            Err(err) => panic!("{:?}", err)
-        }; 
-
-        let clear_values = [0.0, 0.3, 0.6, 1.0];
-
-        //let fragment_color_subbuffer = {
-        //    let elapsed = (start.elapsed().as_millis() % 1000) as f32 / 1000.0;           
-        //    let data = frag::ty::ColorData {
-        //        color_data: [elapsed, 0.5, 0.5].into()
-        //    };  
-        //    fragment_color_buffer.next(data).expect("Could not generate next triangle color")
-        //}; 
-
-        //let set = Arc::new(PersistentDescriptorSet::start(pipeline.clone(), 0)
-        //    .add_buffer(fragment_color_subbuffer).expect("Could not add fragment subbuffer to descriptor set")
-        //    .build().unwrap());
-
-        let command_buffer = AutoCommandBufferBuilder::primary_one_time_submit(device.clone(), queue.family()).unwrap()
-            .begin_render_pass(framebuffers[image_num].clone(), false,
-                vec!(clear_values.into(), 1f32.into())).unwrap()
-            .draw_indexed(pipeline.clone(), &DynamicState::none(), vec!(vertex_buffer.clone(), normals_buffer.clone()), 
-                  v_index_buffer.clone(), (), ()).unwrap()
-            .end_render_pass().unwrap()
-            .build().unwrap();
-        
-        let future = previous_frame_end.join(acquire_future)
-            .then_execute(queue.clone(), command_buffer).expect("Failure executing command buffer")
-            .then_swapchain_present(queue.clone(), swapchain.clone(), image_num)
-            .then_signal_fence_and_flush();
-
-        match future {
-            Ok(future) => previous_frame_end = Box::new(future) as Box<_>,
-            Err(FlushError::OutOfDate) => {
-                recreate_swapchain = true;
-                previous_frame_end = Box::new(sync::now(device.clone())) as Box<_>;
-            }
-            Err(err) => {
-                println!("{:?}", err);
-                previous_frame_end = Box::new(sync::now(device.clone())) as Box<_>;
-            }  
-        } 
+        let now = Instant::now();
+        let dt = now.duration_since(last_frame);
+        last_frame = now;
+        controls = input::advance(controls, dt.as_secs() as f32 + dt.subsec_nanos() as f32 / 1_000_000_000.0);
+        controls.apply(&mut camera);
+
+        if let Err(err) = renderer.draw_frame(&camera, model_matrix) {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+        //Frame pacing in the title bar, refreshed once a second so it stays readable
+        if now.duration_since(last_stats) >= Duration::from_secs(1) {
+            last_stats = now;
+            renderer.window().set_title(&format!("{} - {}", WINDOW_TITLE, renderer.frame_stats()));
+        }
 
+        let mut resized = false;
+        let mut cycle_present = false;
         events_loop.poll_events(|event| {
+            controls = input::handle_event(controls, &event);
+            cycle_present |= input::present_policy_cycled(&event);
+            //Toggled with the number keys
+            if let Some(&mesh) = input::toggled_mesh(&event).and_then(|i| meshes.get(i)) {
+                let visible = renderer.is_visible(mesh);
+                renderer.set_visible(mesh, !visible);
+            }
             match event {
                 Event::WindowEvent { event: WindowEvent::CloseRequested, .. } => done = true,
-                Event::WindowEvent { event: WindowEvent::Resized(_), .. } => recreate_swapchain = true,
+                Event::WindowEvent { event: WindowEvent::Resized(_), .. } => resized = true,
                 _ => ()
-            }  
-        }); 
-        if done { return; } 
-
-    }
-
-}
-
-//TODO: Error handling
-fn init_vulkan() -> Result<(Arc<Device>, QueuesIter, Arc<Surface<Window>>, EventsLoop), Box<Error>> {
-        
-    let instance = {
-        let extensions = vulkano_win::required_extensions();
-        let info = app_info_from_cargo_toml!();
-        Instance::new(Some(&info), &extensions, None).expect("Could not create instance")
-    };
-
-    //TODO: Filter devices by
-    //  1.) Optional features needed by my application
-    //  2.) Devices that can draw to my surface
-    //  3.) Let user choose between the rest (or just choose first one after that)
-    let physical_device = PhysicalDevice::enumerate(&instance).next().expect("No devices");
-
-    let events_loop = EventsLoop::new();
-    let surface = WindowBuilder::new()
-        .with_title("Riley's Vulkan Render Engine")
-        .with_decorations(true)
-        .build_vk_surface(&events_loop, instance.clone())
-        .expect("Could not create window");
-
-    let queue_family = physical_device.queue_families().find(|&q| {
-        q.supports_graphics() && surface.is_supported(q).unwrap_or(false)
-    }).expect("Could not find queue.");
-    
-    let device_extensions = DeviceExtensions {
-        khr_swapchain: true,
-        .. DeviceExtensions::none()
-    };
-    let (device, queues) = Device::new(physical_device, physical_device.supported_features(),
-        &device_extensions, [(queue_family, 0.5)].iter().cloned()).expect("Could not create device");
-
-    Ok((device, queues, surface, events_loop))
-} 
-
-fn gen_swapchain(surface: Arc<Surface<Window>>, queue: Arc<Queue>, device: Arc<Device>) 
-    -> Result<(Arc<Swapchain<Window>>, Vec<Arc<SwapchainImage<Window>>>), SwapchainCreationError> {
-        
-    let window = surface.window();
-
-    let capabilities = surface.capabilities(device.physical_device()).unwrap();
-
-    let usage = capabilities.supported_usage_flags;
-
-    let alpha = capabilities.supported_composite_alpha.iter().next().unwrap();
-    
-    //TODO: Choose format based on our needs.
-    let format = capabilities.supported_formats[0].0;
-    
-    //TODO: Use more layers if necessary.
-    let layers = 1;
-
-    let dimensions = get_window_dimensions(&window)?;
-
-    let transform = capabilities.current_transform;
-
-    //Attempt to use triple buffering
-    let buffer_count = if let Some(limit) = capabilities.max_image_count {
-        min(3, limit)
-    } else { 
-        max(3, capabilities.min_image_count) 
-    };
-
-    let clip = true; //Clip parts of the buffer which aren't visible
-
-    let present_mode = PresentMode::Fifo;
-
-    Swapchain::new(device.clone(), surface.clone(), buffer_count, format, dimensions,
-        layers, usage, &queue, transform, alpha, present_mode, clip, None)
-}
-
-fn load_texture(queue: Arc<Queue>, bytes: &[u8]) ->  
-    Result<(Arc<ImmutableImage<Format>>, CommandBufferExecFuture<NowFuture, AutoCommandBuffer>), ImageCreationError> {
-
-    let image = image::load_from_memory_with_format(bytes,
-        ImageFormat::PNG).expect("Could not load image").to_rgba(); 
-    let w = image.width();
-    let h = image.height();
-    let image_data = image.into_raw().clone();
-
-    ImmutableImage::from_iter(image_data.iter().cloned(),
-        Dimensions::Dim2d { width: w, height: h },
-        Format::R8G8B8A8Srgb,
-        queue.clone())
-} 
-
-fn gen_framebuffers_from_window_size(
-    images: &[Arc<SwapchainImage<Window>>],
-    render_pass: Arc<RenderPassAbstract + Send + Sync>,
-    device: Arc<Device>,
-    vs: &vertex::Shader,
-    fs: &frag::Shader,
-    ) -> (Arc<GraphicsPipelineAbstract + Send + Sync>, Vec<Arc<FramebufferAbstract + Send + Sync>>) {
-
-    let dimensions = images[0].dimensions();
-
-    let viewport = Viewport {
-        origin: [0.0, 0.0],
-        dimensions: [dimensions[0] as f32, dimensions[1] as f32],
-        depth_range: 0.0..1.0
-    };
-
-    let depth_buffer = AttachmentImage::transient(device.clone(), dimensions, Format::D16Unorm)
-        .expect("Failed to create depth buffer");
-
-    let framebuffers = images.iter().map(|image| {
-        Arc::new(
-            Framebuffer::start(render_pass.clone())
-                .add(image.clone()).unwrap()
-                .add(depth_buffer.clone()).unwrap()
-                .build().unwrap()
-        ) as Arc<FramebufferAbstract + Send + Sync>    
-    }).collect::<Vec<_>>();
-
-    let pipeline = Arc::new(GraphicsPipeline::start()
-        .vertex_input(TwoBuffersDefinition::<Vertex, Normal>::new())
-        .vertex_shader(vs.main_entry_point(), ())
-        .triangle_list()
-        .viewports_dynamic_scissors_irrelevant(1)
-        .viewports(std::iter::once(viewport))
-        .fragment_shader(fs.main_entry_point(), ())
-        .depth_stencil_simple_depth()
-        .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
-        .build(device.clone())
-        .expect("Could not generate graphics pipeline"));
-    
-    (pipeline, framebuffers)
-}
-
-fn get_window_dimensions(window: &Window) -> Result<[u32;2], SwapchainCreationError> {
-    
-    //NOTE: We could set this to capabilities.current_extent.unwrap_or(DEFAULT..)
-    //But since either way we want the initial dimensions to be the window dimensions
-    //we just get the physical dimensions this way
-    if let Some(dimensions) = window.get_inner_size() {
-        let dimensions: (u32, u32) = dimensions.to_physical(window.get_hidpi_factor()).into();
-        Ok([dimensions.0, dimensions.1])
-    } else {
-       Err(SwapchainCreationError::SurfaceLost) 
-    }  
-}  
+            }
+        });
+        if done { return; }
+        if resized {
+            if let Err(err) = renderer.resize() {
+                eprintln!("Could not resize: {}", err);
+                std::process::exit(1);
+            }
+        }
+        if cycle_present {
+            let policy = renderer.present_policy().next();
+            println!("Present mode: {}", policy);
+            if let Err(err) = renderer.set_present_policy(policy) {
+                eprintln!("Could not change the present mode: {}", err);
+                std::process::exit(1);
+            }
+        }
 
-mod vertex {
-    vulkano_shaders::shader!{
-        ty: "vertex",
-        path: "src/vertex.glsl"
     }
-}
 
-mod frag {
-    vulkano_shaders::shader!{
-        ty: "fragment",
-        path: "src/frag.glsl"
-    }
 }
-
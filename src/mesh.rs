@@ -0,0 +1,92 @@
+use super::{Indices, ModelVertex};
+use super::camera::Bounds;
+use super::light::Lighting;
+use super::load::LoadError;
+use super::mtl::{self, Material};
+use super::objload;
+use super::teapot;
+use cgmath::Point3;
+use std::fs;
+use std::path::Path;
+use std::vec::Vec;
+
+#[derive(Clone, Debug)]
+pub struct Mesh {
+    pub name: String,
+    pub material: Option<String>,
+    pub vertices: Vec<ModelVertex>,
+    //Triangle list
+    pub indices: Indices,
+    pub lines: Indices,
+    pub points: Indices,
+}
+
+impl Mesh {
+    pub fn teapot() -> Self {
+        let vertices = teapot::VERTICES.iter().zip(teapot::NORMALS.iter()).map(|(v, n)| ModelVertex {
+            position: v.position,
+            normal: n.normal,
+            tex_coord: (0.0, 0.0),
+        }).collect();
+
+        Mesh {
+            name: "teapot".to_string(),
+            material: None,
+            vertices,
+            indices: Indices::U16(teapot::INDICES.to_vec()),
+            lines: Indices::U16(Vec::new()),
+            points: Indices::U16(Vec::new()),
+        }
+    }
+
+}
+
+pub struct Model {
+    pub meshes: Vec<Mesh>,
+    pub materials: Vec<Material>,
+    //OBJ files have no lights, loaded models get the default ones
+    pub lighting: Lighting,
+}
+
+impl Model {
+    pub fn teapot() -> Self {
+        Model {
+            meshes: vec![Mesh::teapot()],
+            materials: Vec::new(),
+            lighting: Lighting::default(),
+        }
+    }
+
+    //None when there are no vertices to frame
+    pub fn bounds(&self) -> Option<Bounds> {
+        Bounds::from_points(self.meshes.iter().flat_map(|mesh| mesh.vertices.iter())
+            .map(|v| Point3::new(v.position.0, v.position.1, v.position.2)))
+    }
+
+    //Index into `materials` for every mesh, None where the mesh has no material or it isn't in the library
+    pub fn mesh_materials(&self) -> Vec<Option<usize>> {
+        self.meshes.iter().map(|mesh| {
+            mesh.material.as_ref().and_then(|name| self.materials.iter().position(|m| &m.name == name))
+        }).collect()
+    }
+}
+
+//Every object and group in the file becomes its own mesh. A missing material library only warns,
+//the meshes are drawn with the default material instead.
+pub fn load_obj(path: &Path) -> Result<Model, LoadError> {
+    let contents = fs::read_to_string(path)?;
+    let (meshes, library) = objload::load_model(&contents)?;
+
+    let materials = match library {
+        Some(library) => {
+            let library = path.parent().unwrap_or_else(|| Path::new("")).join(library);
+            mtl::load_materials(&library).unwrap_or_else(|err| {
+                warn!("Could not load material library {}: {}", library.display(), err);
+                Vec::new()
+            })
+        },
+        None => Vec::new(),
+    };
+
+    Ok(Model { meshes, materials, lighting: Lighting::default() })
+}
@@ -0,0 +1,156 @@
+use super::load::{statements, LoadError};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::vec::Vec;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Material {
+    pub name: String,
+    //Kd
+    pub diffuse: [f32; 3],
+    //Ks
+    pub specular: [f32; 3],
+    //Ns
+    pub shininess: f32,
+    //d, or 1 - Tr
+    pub alpha: f32,
+    //map_Kd and map_Bump, resolved against the directory of the mtl file
+    pub diffuse_map: Option<PathBuf>,
+    pub bump_map: Option<PathBuf>,
+}
+
+impl Default for Material {
+    //Matches what the renderer drew before materials were supported
+    fn default() -> Self {
+        Material {
+            name: String::new(),
+            diffuse: [1.0, 0.0, 0.0],
+            specular: [0.0, 0.0, 0.0],
+            shininess: 1.0,
+            alpha: 1.0,
+            diffuse_map: None,
+            bump_map: None,
+        }
+    }
+}
+
+pub fn load_materials(path: &Path) -> Result<Vec<Material>, LoadError> {
+    let contents = fs::read_to_string(path)?;
+    parse_materials(&contents, path.parent().unwrap_or_else(|| Path::new("")))
+}
+
+//wavefront_obj's own mtl parser expects every statement in a fixed order and knows no bump maps,
+//so exported files are read with this more forgiving one instead
+pub fn parse_materials(contents: &str, directory: &Path) -> Result<Vec<Material>, LoadError> {
+    let mut materials: Vec<Material> = Vec::new();
+
+    for (line_number, keyword, args) in statements(contents) {
+        if keyword == "newmtl" {
+            let name = args.join(" ");
+            if name.is_empty() {
+                return Err(LoadError::Parse { line: line_number, message: "`newmtl` expects a name".to_string() });
+            }
+            materials.push(Material { name, ..Material::default() });
+            continue;
+        }
+
+        let material = match materials.last_mut() {
+            Some(material) => material,
+            None => return Err(LoadError::Parse {
+                line: line_number,
+                message: format!("`{}` before the first `newmtl`", keyword),
+            }),
+        };
+
+        let numbers = || args.iter().map(|a| a.parse::<f32>()).collect::<Result<Vec<_>, _>>().map_err(|_| {
+            LoadError::Parse { line: line_number, message: format!("`{}` expects numbers", keyword) }
+        });
+        let color = || numbers().and_then(|c| match c.len() {
+            //A single value is a grey
+            1 => Ok([c[0], c[0], c[0]]),
+            3 => Ok([c[0], c[1], c[2]]),
+            _ => Err(LoadError::Parse { line: line_number, message: format!("`{}` expects 1 or 3 values", keyword) }),
+        });
+        let scalar = || numbers().and_then(|c| c.first().cloned().ok_or(LoadError::Parse {
+            line: line_number,
+            message: format!("`{}` expects a value", keyword),
+        }));
+        //Texture statements may have options like `-bm 0.5` before the file name, which always comes last
+        let map = || args.last().map(|file| directory.join(file)).ok_or(LoadError::Parse {
+            line: line_number,
+            message: format!("`{}` expects a file name", keyword),
+        });
+
+        match keyword {
+            "Kd" => material.diffuse = color()?,
+            "Ks" => material.specular = color()?,
+            "Ns" => material.shininess = scalar()?,
+            "d" => material.alpha = scalar()?,
+            "Tr" => material.alpha = 1.0 - scalar()?,
+            "map_Kd" => material.diffuse_map = Some(map()?),
+            "map_Bump" | "map_bump" | "bump" => material.bump_map = Some(map()?),
+            //Ambient, emissive, illumination models and the like don't map onto anything we draw
+            _ => (),
+        }
+    }
+
+    Ok(materials)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn material(statements: &str) -> Material {
+        let mut materials = parse_materials(&format!("newmtl test\n{}", statements), Path::new("models")).unwrap();
+        assert_eq!(materials.len(), 1);
+        materials.remove(0)
+    }
+
+    fn error_line(contents: &str) -> usize {
+        match parse_materials(contents, Path::new("")) {
+            Err(LoadError::Parse { line, .. }) => line,
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn colors_take_one_or_three_values() {
+        assert_eq!(material("Kd 0.5\n").diffuse, [0.5, 0.5, 0.5]);
+        assert_eq!(material("Kd 0.1 0.2 0.3\n").diffuse, [0.1, 0.2, 0.3]);
+        assert_eq!(material("Ks 1\n").specular, [1.0, 1.0, 1.0]);
+        assert_eq!(error_line("newmtl test\nKd 0.1 0.2\n"), 2);
+        assert_eq!(error_line("newmtl test\nKd 0.1 red 0.3\n"), 2);
+    }
+
+    #[test]
+    fn transparency_is_the_inverse_of_alpha() {
+        assert_eq!(material("Tr 0.25\n").alpha, 0.75);
+        assert_eq!(material("d 0.25\n").alpha, 0.25);
+        assert_eq!(error_line("newmtl test\n\nTr\n"), 3);
+    }
+
+    #[test]
+    fn map_options_come_before_the_file_name() {
+        let material = material("map_Kd -s 2 2 1 -clamp on diffuse.png\nmap_Bump -bm 0.5 bump.png\n");
+        assert_eq!(material.diffuse_map, Some(Path::new("models").join("diffuse.png")));
+        assert_eq!(material.bump_map, Some(Path::new("models").join("bump.png")));
+        assert_eq!(error_line("newmtl test\nmap_Kd\n"), 2);
+    }
+
+    #[test]
+    fn statements_apply_to_the_last_material() {
+        let materials = parse_materials("newmtl red paint\nKd 1 0 0\nnewmtl blue\nKd 0 0 1 # comment\nillum 2\n",
+            Path::new("")).unwrap();
+        let names = materials.iter().map(|material| material.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["red paint", "blue"]);
+        assert_eq!(materials[0].diffuse, [1.0, 0.0, 0.0]);
+        assert_eq!(materials[1].diffuse, [0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn statements_before_newmtl_are_rejected() {
+        assert_eq!(error_line("# header\n\nKd 1 1 1\nnewmtl test\n"), 3);
+        assert_eq!(error_line("newmtl\n"), 1);
+    }
+}
@@ -1,53 +1,351 @@
 extern crate wavefront_obj;
 
-use super::{IndexType, Vertex, Indices, Normal, TexVert};
+use super::{Indices, ModelVertex, Vertex, Normal, TexVert};
+use super::load::{statements, LoadError};
+use super::mesh::Mesh;
+use cgmath::{InnerSpace, Vector3};
+use std::collections::HashMap;
 use std::vec::Vec;
-use wavefront_obj::obj::parse;
+use wavefront_obj::obj::{parse, GroupName, Object, Shape, VTNIndex};
 use wavefront_obj::obj::Primitive;
 
-pub fn load_model(contents: &str) -> std::io::Result<(Vec<Vertex>, Vec<TexVert>, Vec<Normal>, Indices)> {
-    let objs = parse(contents).expect("Could not parse obj file"); 
-
-    println!("Loading obj file...");
-
-    //TODO: Optimize. In place?
-    let vertices: Vec<Vertex> = objs.objects[0].vertices.iter().cloned().map(|v| v.into()).collect::<_>();
-    let normals: Vec<Normal> = objs.objects[0].normals.iter().cloned().map(|n| n.into()).collect::<_>();
-    let tvertices: Vec<TexVert> = objs.objects[0].tex_vertices.iter().cloned().map(|vt| vt.into()).collect::<_>();
-
-    //TODO: Support different shape types
-    let mut v_idxs = Vec::new();
-    let mut vn_idxs = Vec::new();
-    let mut vt_idxs = Vec::new();
-    for geometry in objs.objects[0].geometry.iter().cloned() {
-        for shape in geometry.shapes.iter().cloned() {
-           if let Primitive::Triangle(a, b, c) = shape.primitive {
-               v_idxs.push(a.0 as IndexType); 
-               v_idxs.push(b.0 as IndexType); 
-               v_idxs.push(c.0 as IndexType); 
-               if let (Some(at), Some(bt), Some(ct)) = (a.1, b.1, c.1) {
-                   vt_idxs.push(at as IndexType); 
-                   vt_idxs.push(bt as IndexType); 
-                   vt_idxs.push(ct as IndexType); 
-               } 
-               if let (Some(an), Some(bn), Some(cn)) = (a.2, b.2, c.2) {
-                   vn_idxs.push(an as IndexType); 
-                   vn_idxs.push(bn as IndexType); 
-                   vn_idxs.push(cn as IndexType); 
-               } 
-           }  
-        }
-    } 
-
-    let indices = Indices {
-        v: v_idxs,
-        vn: vn_idxs,
-        vt: vt_idxs,
+//Statements for free-form curves and surfaces, which are valid OBJ but can't be drawn by us
+const FREEFORM_STATEMENTS: [&str; 15] = [
+    "vp", "cstype", "deg", "bmat", "step", "curv", "curv2", "surf", "parm", "trim", "hole", "scrv", "sp", "end", "con",
+];
+
+//Also returns the name of the material library the file refers to, if any
+pub fn load_model(contents: &str) -> Result<(Vec<Mesh>, Option<String>), LoadError> {
+    check_statements(contents)?;
+    let (source, line_map) = split_primitives(contents);
+    let objs = parse(&source).map_err(|e| LoadError::Parse {
+        //The parser reports a missing argument on the line after it, check_statements catches those first
+        line: line_map.get(e.line_number - 1).or_else(|| line_map.last()).cloned().unwrap_or(e.line_number),
+        message: e.message,
+    })?;
+
+    let mut meshes = Vec::new();
+    for object in objs.objects.iter() {
+        for geometry in object.geometry.iter() {
+            //A geometry holds every shape between two usemtl statements, split it further by group
+            let mut groups: Vec<(&[GroupName], Vec<&Shape>)> = Vec::new();
+            for shape in geometry.shapes.iter() {
+                match groups.iter_mut().find(|(names, _)| *names == &shape.groups[..]) {
+                    Some((_, shapes)) => shapes.push(shape),
+                    None => groups.push((&shape.groups[..], vec![shape])),
+                }
+            }
+
+            for (names, shapes) in groups {
+                let mesh = build_mesh(object, &shapes, mesh_name(&object.name, names, meshes.len()),
+                    geometry.material_name.clone())?;
+                if !mesh.indices.is_empty() || !mesh.lines.is_empty() || !mesh.points.is_empty() {
+                    meshes.push(mesh);
+                }
+            }
+        }
+    }
+
+    if meshes.is_empty() {
+        return Err(LoadError::NoGeometry);
+    }
+
+    debug!("Loaded {} meshes from the obj file", meshes.len());
+
+    Ok((meshes, objs.material_library))
+}
+
+//Vulkan binds a single index buffer, so every distinct (v, vt, vn) corner becomes its own interleaved vertex.
+//Triangles, lines and points share the vertices but are drawn from separate index batches.
+fn build_mesh(object: &Object, shapes: &[&Shape], name: String, material: Option<String>) -> Result<Mesh, LoadError> {
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    let mut lines = Vec::new();
+    let mut points = Vec::new();
+    let mut unique: HashMap<VTNIndex, u32> = HashMap::new();
+
+    for shape in shapes.iter() {
+        let (corners, batch) = match shape.primitive {
+            Primitive::Triangle(a, b, c) => (vec![a, b, c], &mut indices),
+            Primitive::Line(a, b) => (vec![a, b], &mut lines),
+            Primitive::Point(a) => (vec![a], &mut points),
+        };
+        for corner in corners.iter() {
+            let index = match unique.get(corner) {
+                Some(&index) => index,
+                None => {
+                    if vertices.len() > u32::max_value() as usize {
+                        return Err(LoadError::TooManyVertices { mesh: name });
+                    }
+                    vertices.push(model_vertex(object, corner));
+                    unique.insert(*corner, (vertices.len() - 1) as u32);
+                    (vertices.len() - 1) as u32
+                }
+            };
+            batch.push(index);
+        }
+    }
+
+    let missing_normals = unique.iter()
+        .filter(|(corner, _)| corner.2.is_none())
+        .map(|(_, &index)| index)
+        .collect::<Vec<_>>();
+    if !missing_normals.is_empty() {
+        generate_normals(&mut vertices, &indices, &missing_normals);
+    }
+
+    let count = vertices.len();
+    Ok(Mesh {
+        name,
+        material,
+        vertices,
+        indices: Indices::new(indices, count),
+        lines: Indices::new(lines, count),
+        points: Indices::new(points, count),
+    })
+}
+
+fn mesh_name(object: &str, groups: &[GroupName], number: usize) -> String {
+    let groups = groups.join(",");
+    match (object.is_empty(), groups.is_empty()) {
+        (true, true) => format!("mesh {}", number),
+        (true, false) => groups,
+        (false, true) => object.to_string(),
+        (false, false) => format!("{}/{}", object, groups),
+    }
+}
+
+fn model_vertex(object: &Object, &(v, vt, vn): &VTNIndex) -> ModelVertex {
+    let position: Vertex = object.vertices[v].into();
+    let normal: Normal = vn.map(|vn| object.normals[vn].into())
+        .unwrap_or(Normal { normal: (0.0, 0.0, 0.0) });
+    let tex_coord: TexVert = vt.map(|vt| object.tex_vertices[vt].into())
+        .unwrap_or(TexVert { position2D: (0.0, 0.0) });
+
+    ModelVertex {
+        position: position.position,
+        normal: normal.normal,
+        //OBJ puts v = 0 at the bottom of the image, Vulkan at the top row
+        tex_coord: (tex_coord.position2D.0, 1.0 - tex_coord.position2D.1),
+    }
+}
+
+//Smooth normals for the given vertices, averaged from the faces around them. Faces aren't normalized first,
+//so larger faces weigh more. Vertices only used by lines or points are left without a normal.
+fn generate_normals(vertices: &mut [ModelVertex], indices: &[u32], targets: &[u32]) {
+    let position = |v: &ModelVertex| Vector3::new(v.position.0, v.position.1, v.position.2);
+
+    let mut sums = vec![Vector3::new(0.0, 0.0, 0.0); vertices.len()];
+    for tri in indices.chunks(3) {
+        let (a, b, c) = (tri[0] as usize, tri[1] as usize, tri[2] as usize);
+        let n = (position(&vertices[b]) - position(&vertices[a]))
+            .cross(position(&vertices[c]) - position(&vertices[a]));
+        sums[a] += n;
+        sums[b] += n;
+        sums[c] += n;
+    }
+
+    for &i in targets {
+        let n = sums[i as usize];
+        let n = if n.magnitude2() > 0.0 { n.normalize() } else { n };
+        vertices[i as usize].normal = (n.x, n.y, n.z);
+    }
+}
+
+//wavefront_obj loops forever on a statement it can't consume instead of returning an error,
+//so anything it wouldn't accept is rejected here first, along with references to undefined elements
+fn check_statements(contents: &str) -> Result<(), LoadError> {
+    let mut seen_statement = false;
+    let (mut positions, mut tex_coords, mut normals) = (0, 0, 0);
+
+    for (line_number, keyword, args) in statements(contents) {
+        let numbers = |min: usize, max: usize| {
+            args.len() >= min && args.len() <= max && args.iter().all(|a| a.parse::<f64>().is_ok())
+        };
+
+        let problem = match keyword {
+            "v" | "vn" if !numbers(3, 3) => Some(format!("`{}` expects 3 coordinates", keyword)),
+            "vt" if !numbers(1, 3) => Some("`vt` expects 1 to 3 coordinates".to_string()),
+            "f" | "l" | "p" if args.is_empty() => Some(format!("`{}` expects at least one vertex", keyword)),
+            "o" | "usemtl" | "mtllib" if args.is_empty() => Some(format!("`{}` expects a name", keyword)),
+            "s" if args.is_empty() => Some("`s` expects a smoothing group or off".to_string()),
+            "mtllib" if seen_statement => Some("`mtllib` is only supported as the first statement".to_string()),
+            "v" | "vn" | "vt" | "f" | "l" | "p" | "g" | "s" | "o" | "usemtl" | "mtllib" => None,
+            _ if FREEFORM_STATEMENTS.contains(&keyword) => {
+                return Err(LoadError::UnsupportedPrimitive { line: line_number, statement: keyword.to_string() });
+            },
+            _ => Some(format!("unknown statement `{}`", keyword)),
+        };
+        if let Some(message) = problem {
+            return Err(LoadError::Parse { line: line_number, message });
+        }
+
+        match keyword {
+            "v" => positions += 1,
+            "vt" => tex_coords += 1,
+            "vn" => normals += 1,
+            "f" | "l" | "p" => for corner in args.iter() {
+                let parts = corner.split('/').collect::<Vec<_>>();
+                if parts.len() > 3 || parts[0].is_empty() {
+                    return Err(LoadError::Parse {
+                        line: line_number,
+                        message: format!("`{}` is not a v, v/vt, v//vn or v/vt/vn corner", corner),
+                    });
+                }
+                let elements = [("vertex", positions), ("texture coordinate", tex_coords), ("normal", normals)];
+                for (part, &(element, count)) in parts.iter().zip(elements.iter()).filter(|(p, _)| !p.is_empty()) {
+                    let index = part.parse::<isize>().map_err(|_| LoadError::Parse {
+                        line: line_number,
+                        message: format!("expected an index but got `{}`", part),
+                    })?;
+                    if resolve_index(index, count).is_none() {
+                        return Err(LoadError::BadIndex { line: line_number, element, index, count });
+                    }
+                }
+            },
+            _ => (),
+        }
+
+        seen_statement = true;
+    }
+
+    Ok(())
+}
+
+//Zero based index of an OBJ reference, which are one based or negative counting back from the last element
+fn resolve_index(index: isize, count: usize) -> Option<usize> {
+    let index = if index < 0 { count as isize + index } else { index - 1 };
+    if index >= 0 && (index as usize) < count { Some(index as usize) } else { None }
+}
+
+//wavefront_obj fans every `f` and `l` statement into triangles around its last corner, doesn't know `p` at all and
+//miscounts negative indices. Rewrites the source so that polygons are ear clipped, polylines become single segments,
+//points become one corner faces (which the parser turns into Primitive::Point) and every index is absolute.
+//Also returns the original line of every new line. Expects the source to have passed check_statements.
+fn split_primitives(contents: &str) -> (String, Vec<usize>) {
+    let mut source = String::with_capacity(contents.len());
+    let mut line_map = Vec::new();
+    let mut positions: Vec<Vector3<f32>> = Vec::new();
+    let (mut tex_coords, mut normals) = (0, 0);
+
+    for (number, line) in contents.lines().enumerate() {
+        let statement = line.split('#').next().unwrap_or("");
+        let mut tokens = statement.split_whitespace();
+        let keyword = tokens.next().unwrap_or("");
+        let mut corners = tokens.map(|c| c.to_string()).collect::<Vec<_>>();
+        if let "f" | "l" | "p" = keyword {
+            for corner in corners.iter_mut() {
+                *corner = absolute_corner(corner, [positions.len(), tex_coords, normals]);
+            }
+        }
+
+        let mut emit = |text: String| {
+            source.push_str(&text);
+            source.push('\n');
+            line_map.push(number + 1);
+        };
+
+        match keyword {
+            "v" => {
+                let c = corners.iter().map(|c| c.parse::<f32>().unwrap_or(0.0)).collect::<Vec<_>>();
+                positions.push(Vector3::new(c[0], c[1], c[2]));
+                emit(line.to_string());
+            },
+            "vt" => {
+                tex_coords += 1;
+                emit(line.to_string());
+            },
+            "vn" => {
+                normals += 1;
+                emit(line.to_string());
+            },
+            "f" if corners.len() > 3 => {
+                let polygon = corners.iter().map(|c| positions[position_index(c)]).collect::<Vec<_>>();
+                for tri in triangulate(&polygon) {
+                    emit(format!("f {} {} {}", corners[tri[0]], corners[tri[1]], corners[tri[2]]));
+                }
+            },
+            "l" if corners.len() > 2 => for pair in corners.windows(2) {
+                emit(format!("l {} {}", pair[0], pair[1]));
+            },
+            "p" => for corner in corners.iter() {
+                emit(format!("f {}", corner));
+            },
+            "f" | "l" => emit(format!("{} {}", keyword, corners.join(" "))),
+            _ => emit(line.to_string()),
+        }
+    }
+
+    (source, line_map)
+}
+
+//Rewrites every index of a `v/vt/vn` corner to its one based absolute form
+fn absolute_corner(corner: &str, counts: [usize; 3]) -> String {
+    corner.split('/').zip(counts.iter()).map(|(part, &count)| {
+        match part.parse::<isize>().ok().and_then(|index| resolve_index(index, count)) {
+            Some(index) => (index + 1).to_string(),
+            None => part.to_string(),
+        }
+    }).collect::<Vec<_>>().join("/")
+}
+
+//Zero based position index of a corner that went through absolute_corner
+fn position_index(corner: &str) -> usize {
+    corner.split('/').next().and_then(|i| i.parse::<usize>().ok()).unwrap_or(1) - 1
+}
+
+//Ear clipping in the plane the polygon is most aligned with. Keeps the winding of the polygon and falls back to a
+//fan if the polygon is degenerate enough that no ear can be found.
+fn triangulate(polygon: &[Vector3<f32>]) -> Vec<[usize; 3]> {
+    //Newell's method, robust for non planar and concave polygons
+    let mut normal = Vector3::new(0.0, 0.0, 0.0);
+    for (i, a) in polygon.iter().enumerate() {
+        let b = polygon[(i + 1) % polygon.len()];
+        normal += Vector3::new((a.y - b.y) * (a.z + b.z), (a.z - b.z) * (a.x + b.x), (a.x - b.x) * (a.y + b.y));
+    }
+    let (nx, ny, nz) = (normal.x.abs(), normal.y.abs(), normal.z.abs());
+    let flat = polygon.iter().map(|p| {
+        if nx >= ny && nx >= nz { (p.y, p.z) } else if ny >= nz { (p.z, p.x) } else { (p.x, p.y) }
+    }).collect::<Vec<_>>();
+
+    let cross = |a: usize, b: usize, c: usize| {
+        (flat[b].0 - flat[a].0) * (flat[c].1 - flat[a].1) - (flat[b].1 - flat[a].1) * (flat[c].0 - flat[a].0)
     };
+    let area: f32 = (0..flat.len()).map(|i| {
+        let j = (i + 1) % flat.len();
+        flat[i].0 * flat[j].1 - flat[j].0 * flat[i].1
+    }).sum();
+    let winding = if area < 0.0 { -1.0 } else { 1.0 };
+
+    let mut remaining = (0..polygon.len()).collect::<Vec<_>>();
+    let mut triangles = Vec::with_capacity(polygon.len() - 2);
 
-    println!("Finished loading obj");
+    while remaining.len() > 3 {
+        let n = remaining.len();
+        let ear = (0..n).find(|&i| {
+            let (a, b, c) = (remaining[(i + n - 1) % n], remaining[i], remaining[(i + 1) % n]);
+            if cross(a, b, c) * winding <= 0.0 {
+                return false;
+            }
+            remaining.iter().filter(|&&p| p != a && p != b && p != c).all(|&p| {
+                cross(a, b, p) * winding < 0.0 || cross(b, c, p) * winding < 0.0 || cross(c, a, p) * winding < 0.0
+            })
+        });
 
-    Ok((vertices, tvertices, normals, indices))
+        match ear {
+            Some(i) => {
+                triangles.push([remaining[(i + n - 1) % n], remaining[i], remaining[(i + 1) % n]]);
+                remaining.remove(i);
+            },
+            None => break,
+        }
+    }
+
+    for i in 1..remaining.len() - 1 {
+        triangles.push([remaining[0], remaining[i], remaining[i + 1]]);
+    }
+
+    triangles
 }
 
 //TODO: 3d textures?
@@ -74,3 +372,209 @@ impl From<wavefront_obj::obj::Vertex> for Vertex {
         }  
    } 
 } 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn single_mesh(contents: &str) -> Mesh {
+        let (mut meshes, _) = load_model(contents).unwrap();
+        assert_eq!(meshes.len(), 1);
+        meshes.remove(0)
+    }
+
+    fn indices(indices: &Indices) -> Vec<u32> {
+        match indices {
+            Indices::U16(indices) => indices.iter().map(|&i| i as u32).collect(),
+            Indices::U32(indices) => indices.clone(),
+        }
+    }
+
+    const QUAD_ELEMENTS: &str = "
+        v 0 0 0
+        v 1 0 0
+        v 1 1 0
+        v 0 1 0
+        vt 0 0
+        vt 1 0
+        vt 1 1
+        vt 0 1
+        vn 0 0 1
+    ";
+
+    #[test]
+    fn quad_shares_its_corners() {
+        let mesh = single_mesh(&format!("{}f 1/1/1 2/2/1 3/3/1 4/4/1\n", QUAD_ELEMENTS));
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.indices.len(), 6);
+        assert!(mesh.lines.is_empty() && mesh.points.is_empty());
+    }
+
+    #[test]
+    fn separate_triangles_share_identical_corners() {
+        let mesh = single_mesh(&format!("{}f 1/1/1 2/2/1 3/3/1\nf 1/1/1 3/3/1 4/4/1\n", QUAD_ELEMENTS));
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.indices.len(), 6);
+        assert!(indices(&mesh.indices).iter().all(|&i| i < 4));
+    }
+
+    #[test]
+    fn reused_position_with_another_tex_coord_is_a_new_vertex() {
+        let mesh = single_mesh(&format!("{}f 1/1/1 2/2/1 3/3/1\nf 1/4/1 3/3/1 4/4/1\n", QUAD_ELEMENTS));
+        assert_eq!(mesh.vertices.len(), 5);
+        assert_eq!(mesh.indices.len(), 6);
+        let mut origin = mesh.vertices.iter()
+            .filter(|vertex| vertex.position == (0.0, 0.0, 0.0))
+            .map(|vertex| vertex.tex_coord)
+            .collect::<Vec<_>>();
+        origin.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        //Flipped to Vulkan's top row origin
+        assert_eq!(origin, vec![(0.0, 0.0), (0.0, 1.0)]);
+    }
+
+    #[test]
+    fn reused_position_with_another_normal_is_a_new_vertex() {
+        let mesh = single_mesh("v 0 0 0\nv 1 0 0\nv 0 1 0\nvn 0 0 1\nvn 0 0 -1\nf 1//1 2//1 3//1\nf 1//2 3//2 2//2\n");
+        assert_eq!(mesh.vertices.len(), 6);
+        let up = mesh.vertices.iter().filter(|vertex| vertex.normal == (0.0, 0.0, 1.0)).count();
+        let down = mesh.vertices.iter().filter(|vertex| vertex.normal == (0.0, 0.0, -1.0)).count();
+        assert_eq!((up, down), (3, 3));
+    }
+
+    #[test]
+    fn small_meshes_use_u16_indices() {
+        let mesh = single_mesh("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n");
+        match mesh.indices {
+            Indices::U16(ref indices) => assert_eq!(indices.len(), 3),
+            Indices::U32(_) => panic!("expected u16 indices"),
+        }
+    }
+
+    #[test]
+    fn missing_normals_are_generated_from_the_faces() {
+        let mesh = single_mesh("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n");
+        for vertex in mesh.vertices.iter() {
+            assert_eq!(vertex.normal, (0.0, 0.0, 1.0));
+        }
+    }
+
+    #[test]
+    fn every_object_group_and_material_is_kept() {
+        let contents = "
+            mtllib scene.mtl
+            o lid
+            v 0 0 1
+            v 1 0 1
+            v 0 1 1
+            f 1 2 3
+            g handle
+            l 1 2 3
+            o body
+            v 0 0 0
+            v 1 0 0
+            v 0 1 0
+            v 1 1 0
+            usemtl red
+            f 4 5 6
+            usemtl blue
+            f 5 7 6
+            p 7
+        ";
+        let (meshes, material_library) = load_model(contents).unwrap();
+        assert_eq!(material_library, Some("scene.mtl".to_string()));
+
+        let summary = meshes.iter()
+            .map(|mesh| (mesh.name.as_str(), mesh.material.as_deref(),
+                mesh.indices.len(), mesh.lines.len(), mesh.points.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(summary, vec![
+            ("lid", None, 3, 0, 0),
+            ("lid/handle", None, 0, 4, 0),
+            ("body", Some("red"), 3, 0, 0),
+            ("body", Some("blue"), 3, 0, 1),
+        ]);
+    }
+
+    //A pentagon followed by the given lines, line 9 is the first one after it
+    fn after_pentagon(lines: &str) -> Result<(Vec<Mesh>, Option<String>), LoadError> {
+        load_model(&format!("v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nv 0 2 0\nvt 0 0\nvn 0 0 1\nf 1 2 3 4 5\n{}", lines))
+    }
+
+    #[test]
+    fn bad_index_after_a_polygon_reports_the_original_line() {
+        match after_pentagon("f 1 2 3\nf 1 2 9\n") {
+            Err(LoadError::BadIndex { line, element, index, count }) =>
+                assert_eq!((line, element, index, count), (10, "vertex", 9, 5)),
+            other => panic!("unexpected {:?}", other),
+        }
+        match after_pentagon("f 1/2 2/1 3/1\n") {
+            Err(LoadError::BadIndex { line, element, index, count }) =>
+                assert_eq!((line, element, index, count), (9, "texture coordinate", 2, 1)),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parser_errors_after_a_polygon_report_the_original_line() {
+        //The pentagon is three lines once split, so these only match when mapped back
+        match after_pentagon("s 1\ns foo\n") {
+            Err(LoadError::Parse { line, .. }) => assert_eq!(line, 10),
+            other => panic!("unexpected {:?}", other),
+        }
+        for missing in ["o", "s", "usemtl"].iter() {
+            match after_pentagon(&format!("{}\nv 0 0 0\n", missing)) {
+                Err(LoadError::Parse { line, .. }) => assert_eq!(line, 9, "`{}` without an argument", missing),
+                other => panic!("unexpected {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn negative_indices_count_back_from_the_last_element() {
+        let mesh = single_mesh("v 0 0 0\nv 1 0 0\nv 0 1 0\nf -3 -2 -1\n");
+        assert_eq!(mesh.vertices.len(), 3);
+
+        match after_pentagon("f -1 -2 -6\n") {
+            Err(LoadError::BadIndex { line, element, index, count }) =>
+                assert_eq!((line, element, index, count), (9, "vertex", -6, 5)),
+            other => panic!("unexpected {:?}", other),
+        }
+        match after_pentagon("f 1//-2 2//1 3//1\n") {
+            Err(LoadError::BadIndex { line, element, index, count }) =>
+                assert_eq!((line, element, index, count), (9, "normal", -2, 1)),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn unknown_statements_are_parse_errors() {
+        let err = after_pentagon("f 1 2 3\nfoo 1 2\n").unwrap_err();
+        match err {
+            LoadError::Parse { line: 10, ref message } => assert_eq!(message, "unknown statement `foo`"),
+            ref other => panic!("unexpected {:?}", other),
+        }
+        assert_eq!(err.to_string(), "line 10: unknown statement `foo`");
+    }
+
+    #[test]
+    fn free_form_statements_are_unsupported() {
+        let err = after_pentagon("# a curve\nvp 0.5\ncurv 0 1 1 2\n").unwrap_err();
+        match err {
+            LoadError::UnsupportedPrimitive { line: 10, ref statement } => assert_eq!(statement, "vp"),
+            ref other => panic!("unexpected {:?}", other),
+        }
+
+        match after_pentagon("curv 0 1 1 2\n") {
+            Err(LoadError::UnsupportedPrimitive { line, statement }) => assert_eq!((line, statement.as_str()), (9, "curv")),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn files_without_geometry_are_rejected() {
+        match load_model("# nothing\nv 0 0 0\n") {
+            Err(LoadError::NoGeometry) => (),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
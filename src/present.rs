@@ -0,0 +1,85 @@
+use std::fmt;
+use vulkano::format::Format;
+use vulkano::swapchain::{Capabilities, ColorSpace, PresentMode};
+
+//What the user asked for. The present mode actually used falls back to what the surface supports.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PresentPolicy {
+    //Waits for vertical blank, never tears
+    Vsync,
+    //Replaces the queued frame with newer ones, low latency without tearing
+    Mailbox,
+    //Presents right away and may tear
+    Immediate,
+}
+
+impl Default for PresentPolicy {
+    fn default() -> Self {
+        PresentPolicy::Vsync
+    }
+}
+
+impl fmt::Display for PresentPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PresentPolicy::Vsync => write!(f, "vsync"),
+            PresentPolicy::Mailbox => write!(f, "mailbox"),
+            PresentPolicy::Immediate => write!(f, "immediate"),
+        }
+    }
+}
+
+impl PresentPolicy {
+    pub fn parse(name: &str) -> Option<PresentPolicy> {
+        match name {
+            "vsync" => Some(PresentPolicy::Vsync),
+            "mailbox" => Some(PresentPolicy::Mailbox),
+            "immediate" => Some(PresentPolicy::Immediate),
+            _ => None,
+        }
+    }
+
+    //Cycled through with the V key
+    pub fn next(self) -> PresentPolicy {
+        match self {
+            PresentPolicy::Vsync => PresentPolicy::Mailbox,
+            PresentPolicy::Mailbox => PresentPolicy::Immediate,
+            PresentPolicy::Immediate => PresentPolicy::Vsync,
+        }
+    }
+
+    //Most preferred first. Fifo is last because every surface supports it.
+    fn present_modes(self) -> &'static [PresentMode] {
+        match self {
+            PresentPolicy::Vsync => &[PresentMode::Fifo],
+            PresentPolicy::Mailbox => &[PresentMode::Mailbox, PresentMode::Fifo],
+            PresentPolicy::Immediate => &[PresentMode::Immediate, PresentMode::Mailbox, PresentMode::Fifo],
+        }
+    }
+}
+
+pub fn choose_present_mode(capabilities: &Capabilities, policy: PresentPolicy) -> PresentMode {
+    let modes = policy.present_modes();
+    let mode = modes.iter().cloned().find(|&mode| capabilities.present_modes.supports(mode)).unwrap_or(PresentMode::Fifo);
+    if mode != modes[0] {
+        warn!("The surface doesn't support {:?} presentation for {}, using {:?}", modes[0], policy, mode);
+    }
+    mode
+}
+
+//The shaders write linear colors, an sRGB format encodes them for the display. vulkano only creates
+//swapchains in the sRGB non-linear color space, so formats in other color spaces can't be used.
+const PREFERRED_FORMATS: [Format; 2] = [Format::B8G8R8A8Srgb, Format::R8G8B8A8Srgb];
+
+pub fn choose_format(capabilities: &Capabilities) -> Option<Format> {
+    let formats = capabilities.supported_formats.iter()
+        .filter(|&&(_, color_space)| color_space == ColorSpace::SrgbNonLinear)
+        .map(|&(format, _)| format)
+        .collect::<Vec<_>>();
+
+    let preferred = PREFERRED_FORMATS.iter().cloned().find(|format| formats.contains(format));
+    if preferred.is_none() && !formats.is_empty() {
+        warn!("The surface supports neither {:?}, colors may be off using {:?}", PREFERRED_FORMATS, formats[0]);
+    }
+    preferred.or_else(|| formats.first().cloned())
+}
@@ -0,0 +1,599 @@
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+use vulkano::descriptor::descriptor::{DescriptorDescTy, DescriptorType};
+use vulkano::descriptor::pipeline_layout::PipelineLayoutDesc;
+use vulkano::format::Format;
+use vulkano::pipeline::shader::ShaderInterfaceDef;
+
+const MAGIC: u32 = 0x0723_0203;
+const HEADER_WORDS: usize = 5;
+
+const OP_ENTRY_POINT: u32 = 15;
+const OP_TYPE_BOOL: u32 = 20;
+const OP_TYPE_INT: u32 = 21;
+const OP_TYPE_FLOAT: u32 = 22;
+const OP_TYPE_VECTOR: u32 = 23;
+const OP_TYPE_MATRIX: u32 = 24;
+const OP_TYPE_IMAGE: u32 = 25;
+const OP_TYPE_SAMPLER: u32 = 26;
+const OP_TYPE_SAMPLED_IMAGE: u32 = 27;
+const OP_TYPE_ARRAY: u32 = 28;
+const OP_TYPE_RUNTIME_ARRAY: u32 = 29;
+const OP_TYPE_STRUCT: u32 = 30;
+const OP_TYPE_POINTER: u32 = 32;
+const OP_CONSTANT: u32 = 43;
+const OP_VARIABLE: u32 = 59;
+const OP_DECORATE: u32 = 71;
+const OP_MEMBER_DECORATE: u32 = 72;
+
+const DECORATION_BUFFER_BLOCK: u32 = 3;
+const DECORATION_ARRAY_STRIDE: u32 = 6;
+const DECORATION_MATRIX_STRIDE: u32 = 7;
+const DECORATION_BUILT_IN: u32 = 11;
+const DECORATION_LOCATION: u32 = 30;
+const DECORATION_BINDING: u32 = 33;
+const DECORATION_DESCRIPTOR_SET: u32 = 34;
+const DECORATION_OFFSET: u32 = 35;
+
+const STORAGE_UNIFORM_CONSTANT: u32 = 0;
+const STORAGE_INPUT: u32 = 1;
+const STORAGE_UNIFORM: u32 = 2;
+const STORAGE_OUTPUT: u32 = 3;
+const STORAGE_PUSH_CONSTANT: u32 = 9;
+const STORAGE_STORAGE_BUFFER: u32 = 12;
+
+const DIM_BUFFER: u32 = 5;
+const DIM_SUBPASS_DATA: u32 = 6;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    Vertex,
+    Fragment,
+}
+
+impl Stage {
+    fn execution_model(self) -> u32 {
+        match self {
+            Stage::Vertex => 0,
+            Stage::Fragment => 4,
+        }
+    }
+}
+
+//An input or output variable, matrices and arrays take several locations
+#[derive(Clone, Debug, PartialEq)]
+pub struct Variable {
+    pub location: Range<u32>,
+    pub format: Format,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Descriptor {
+    pub ty: DescriptorType,
+    pub array_count: u32,
+    //Bytes of a uniform or storage buffer block
+    pub block_size: Option<u32>,
+}
+
+//What a shader module needs from the pipeline it's used in
+#[derive(Clone, Debug, PartialEq)]
+pub struct Interface {
+    pub inputs: Vec<Variable>,
+    pub outputs: Vec<Variable>,
+    //By set and binding
+    pub descriptors: BTreeMap<(u32, u32), Descriptor>,
+    //Bytes of the push constant block
+    pub push_constants: Option<u32>,
+}
+
+impl Interface {
+    //The interface vulkano_shaders generated for a shader compiled into the crate. The sizes of its buffer
+    //blocks are the sizes of the generated structs, by set and binding.
+    pub fn generated<I, O, L>(inputs: I, outputs: O, layout: L, block_sizes: &[((u32, u32), usize)]) -> Interface
+        where I: ShaderInterfaceDef, O: ShaderInterfaceDef, L: PipelineLayoutDesc
+    {
+        let variables = |elements: Vec<Variable>| {
+            let mut elements = elements;
+            elements.sort_by_key(|variable| variable.location.start);
+            elements
+        };
+
+        let mut descriptors = BTreeMap::new();
+        for set in 0..layout.num_sets() {
+            for binding in 0..layout.num_bindings_in_set(set).unwrap_or(0) {
+                if let Some(desc) = layout.descriptor(set, binding) {
+                    let key = (set as u32, binding as u32);
+                    let block_size = block_sizes.iter().find(|&&(at, _)| at == key).map(|&(_, size)| size as u32);
+                    descriptors.insert(key, Descriptor { ty: descriptor_type(&desc.ty), array_count: desc.array_count,
+                        block_size });
+                }
+            }
+        }
+
+        let push_constants = (0..layout.num_push_constants_ranges())
+            .filter_map(|num| layout.push_constants_range(num))
+            .map(|range| (range.offset + range.size) as u32)
+            .max();
+
+        Interface {
+            inputs: variables(inputs.elements().map(|e| Variable { location: e.location, format: e.format }).collect()),
+            outputs: variables(outputs.elements().map(|e| Variable { location: e.location, format: e.format }).collect()),
+            descriptors,
+            push_constants,
+        }
+    }
+
+    //Whether a module with this interface can replace one with the expected interface in the same pipeline.
+    //Inputs and outputs have to match exactly, descriptors that are no longer used are fine.
+    pub fn check(&self, expected: &Interface) -> Result<(), String> {
+        if self.inputs != expected.inputs {
+            return Err(format!("inputs changed from {} to {}", variables(&expected.inputs), variables(&self.inputs)));
+        }
+        if self.outputs != expected.outputs {
+            return Err(format!("outputs changed from {} to {}", variables(&expected.outputs), variables(&self.outputs)));
+        }
+
+        for (&(set, binding), descriptor) in &self.descriptors {
+            match expected.descriptors.get(&(set, binding)) {
+                Some(old) if old == descriptor => (),
+                Some(old) => return Err(format!("set {} binding {} changed from {} to {}", set, binding,
+                    describe(old), describe(descriptor))),
+                None => return Err(format!("set {} binding {} is new, {}", set, binding, describe(descriptor))),
+            }
+        }
+
+        match (self.push_constants, expected.push_constants) {
+            (None, _) => Ok(()),
+            (Some(size), Some(old)) if size == old => Ok(()),
+            (Some(size), _) => Err(format!("push constants changed to {} bytes", size)),
+        }
+    }
+}
+
+//Reads the interface of the main entry point of a SPIR-V module
+pub fn reflect(words: &[u32], stage: Stage) -> Result<Interface, String> {
+    let module = Module::parse(words)?;
+    if !module.entry_points.iter().any(|&(model, ref name)| model == stage.execution_model() && name == "main") {
+        return Err(format!("there is no {:?} entry point called main", stage));
+    }
+
+    let mut interface = Interface {
+        inputs: Vec::new(),
+        outputs: Vec::new(),
+        descriptors: BTreeMap::new(),
+        push_constants: None,
+    };
+
+    for &(id, pointer, storage) in &module.variables {
+        let ty = module.pointee(pointer)?;
+        match storage {
+            STORAGE_INPUT | STORAGE_OUTPUT => {
+                if module.is_builtin(id, ty)? {
+                    continue;
+                }
+                let start = module.decoration(id, DECORATION_LOCATION)
+                    .ok_or_else(|| format!("variable {} has no location", id))?;
+                let (format, locations) = module.format(ty)?;
+                let variable = Variable { location: start..start + locations, format };
+                if storage == STORAGE_INPUT {
+                    interface.inputs.push(variable);
+                } else {
+                    interface.outputs.push(variable);
+                }
+            },
+            STORAGE_UNIFORM_CONSTANT | STORAGE_UNIFORM | STORAGE_STORAGE_BUFFER => {
+                let set = module.decoration(id, DECORATION_DESCRIPTOR_SET)
+                    .ok_or_else(|| format!("variable {} has no descriptor set", id))?;
+                let binding = module.decoration(id, DECORATION_BINDING)
+                    .ok_or_else(|| format!("variable {} has no binding", id))?;
+                interface.descriptors.insert((set, binding), module.descriptor(ty, storage)?);
+            },
+            STORAGE_PUSH_CONSTANT => interface.push_constants = Some(module.size(ty, None)?),
+            _ => (),
+        }
+    }
+
+    interface.inputs.sort_by_key(|variable| variable.location.start);
+    interface.outputs.sort_by_key(|variable| variable.location.start);
+    Ok(interface)
+}
+
+//The instructions of a module that describe its interface
+#[derive(Default)]
+struct Module<'a> {
+    entry_points: Vec<(u32, String)>,
+    //Type declarations by result id, starting with the opcode
+    types: HashMap<u32, &'a [u32]>,
+    constants: HashMap<u32, u32>,
+    //Global variables as id, pointer type and storage class
+    variables: Vec<(u32, u32, u32)>,
+    decorations: HashMap<u32, Vec<(u32, u32)>>,
+    member_decorations: HashMap<(u32, u32), Vec<(u32, u32)>>,
+}
+
+impl<'a> Module<'a> {
+    fn parse(words: &'a [u32]) -> Result<Module<'a>, String> {
+        if words.len() < HEADER_WORDS || words[0] != MAGIC {
+            return Err("not a SPIR-V module".to_string());
+        }
+
+        let mut module = Module::default();
+        let mut rest = &words[HEADER_WORDS..];
+        while !rest.is_empty() {
+            let count = (rest[0] >> 16) as usize;
+            if count == 0 || count > rest.len() {
+                return Err("truncated SPIR-V instruction".to_string());
+            }
+            let (instruction, next) = rest.split_at(count);
+            rest = next;
+
+            let operand = |i: usize| operand(instruction, i);
+            match instruction[0] & 0xffff {
+                OP_ENTRY_POINT if count > 3 => module.entry_points.push((operand(1), string(&instruction[3..]))),
+                OP_TYPE_BOOL ..= OP_TYPE_STRUCT | OP_TYPE_POINTER if count > 1 => {
+                    module.types.insert(instruction[1], instruction);
+                },
+                OP_CONSTANT => {
+                    module.constants.insert(operand(2), operand(3));
+                },
+                OP_VARIABLE => module.variables.push((operand(2), operand(1), operand(3))),
+                OP_DECORATE => module.decorations.entry(operand(1)).or_insert_with(Vec::new)
+                    .push((operand(2), operand(3))),
+                OP_MEMBER_DECORATE => module.member_decorations.entry((operand(1), operand(2))).or_insert_with(Vec::new)
+                    .push((operand(3), operand(4))),
+                _ => (),
+            }
+        }
+        Ok(module)
+    }
+
+    //The first literal of a decoration, the decorations read here have exactly one
+    fn decoration(&self, id: u32, decoration: u32) -> Option<u32> {
+        self.decorations.get(&id)?.iter().find(|&&(d, _)| d == decoration).map(|&(_, literal)| literal)
+    }
+
+    fn member_decoration(&self, id: u32, member: u32, decoration: u32) -> Option<u32> {
+        self.member_decorations.get(&(id, member))?.iter().find(|&&(d, _)| d == decoration).map(|&(_, literal)| literal)
+    }
+
+    fn ty(&self, id: u32) -> Result<&'a [u32], String> {
+        self.types.get(&id).cloned().ok_or_else(|| format!("type {} is not declared", id))
+    }
+
+    fn pointee(&self, pointer: u32) -> Result<u32, String> {
+        let ty = self.ty(pointer)?;
+        match ty.get(3) {
+            Some(&pointee) if ty[0] & 0xffff == OP_TYPE_POINTER => Ok(pointee),
+            _ => Err(format!("type {} is not a pointer", pointer)),
+        }
+    }
+
+    fn array_length(&self, id: u32) -> Result<u32, String> {
+        self.constants.get(&id).cloned().ok_or_else(|| format!("array length {} is not a constant", id))
+    }
+
+    //gl_Position and friends, either decorated themselves or members of gl_PerVertex
+    fn is_builtin(&self, variable: u32, ty: u32) -> Result<bool, String> {
+        if self.decoration(variable, DECORATION_BUILT_IN).is_some() {
+            return Ok(true);
+        }
+        let mut ty = ty;
+        loop {
+            let instruction = self.ty(ty)?;
+            match instruction[0] & 0xffff {
+                OP_TYPE_ARRAY | OP_TYPE_RUNTIME_ARRAY => ty = operand(instruction, 2),
+                OP_TYPE_STRUCT => return Ok((0..instruction.len() as u32 - 2)
+                    .any(|member| self.member_decoration(ty, member, DECORATION_BUILT_IN).is_some())),
+                _ => return Ok(false),
+            }
+        }
+    }
+
+    //The vertex attribute format of an input or output and how many locations it takes, the same way
+    //vulkano_shaders maps them
+    fn format(&self, id: u32) -> Result<(Format, u32), String> {
+        let ty = self.ty(id)?;
+        let operand = |i: usize| operand(ty, i);
+        match ty[0] & 0xffff {
+            OP_TYPE_INT => {
+                let format = match (operand(2), operand(3) != 0) {
+                    (8, true) => Format::R8Sint,
+                    (8, false) => Format::R8Uint,
+                    (16, true) => Format::R16Sint,
+                    (16, false) => Format::R16Uint,
+                    (32, true) => Format::R32Sint,
+                    (32, false) => Format::R32Uint,
+                    (64, true) => Format::R64Sint,
+                    (64, false) => Format::R64Uint,
+                    (width, _) => return Err(format!("{} bit integers are not supported", width)),
+                };
+                Ok((format, 1))
+            },
+            OP_TYPE_FLOAT => match operand(2) {
+                32 => Ok((Format::R32Sfloat, 1)),
+                64 => Ok((Format::R64Sfloat, 1)),
+                width => Err(format!("{} bit floats are not supported", width)),
+            },
+            OP_TYPE_VECTOR => {
+                let (component, _) = self.format(operand(2))?;
+                let format = match (component, operand(3)) {
+                    (format, 1) => format,
+                    (Format::R32Sfloat, 2) => Format::R32G32Sfloat,
+                    (Format::R32Sfloat, 3) => Format::R32G32B32Sfloat,
+                    (Format::R32Sfloat, 4) => Format::R32G32B32A32Sfloat,
+                    (Format::R32Sint, 2) => Format::R32G32Sint,
+                    (Format::R32Sint, 3) => Format::R32G32B32Sint,
+                    (Format::R32Sint, 4) => Format::R32G32B32A32Sint,
+                    (Format::R32Uint, 2) => Format::R32G32Uint,
+                    (Format::R32Uint, 3) => Format::R32G32B32Uint,
+                    (Format::R32Uint, 4) => Format::R32G32B32A32Uint,
+                    (format, count) => return Err(format!("vectors of {} {:?} are not supported", count, format)),
+                };
+                Ok((format, 1))
+            },
+            OP_TYPE_MATRIX => {
+                let (format, locations) = self.format(operand(2))?;
+                Ok((format, locations * operand(3)))
+            },
+            OP_TYPE_ARRAY => {
+                let (format, locations) = self.format(operand(2))?;
+                Ok((format, locations * self.array_length(operand(3))?))
+            },
+            _ => Err(format!("type {} can't be an input or output", id)),
+        }
+    }
+
+    fn descriptor(&self, id: u32, storage: u32) -> Result<Descriptor, String> {
+        let mut ty = self.ty(id)?;
+        let mut array_count = 1;
+        match ty[0] & 0xffff {
+            OP_TYPE_ARRAY => {
+                array_count = self.array_length(operand(ty, 3))?;
+                ty = self.ty(operand(ty, 2))?;
+            },
+            OP_TYPE_RUNTIME_ARRAY => return Err("runtime sized descriptor arrays are not supported".to_string()),
+            _ => (),
+        }
+
+        let operand = |i: usize| operand(ty, i);
+        let mut block_size = None;
+        let descriptor_type = match ty[0] & 0xffff {
+            OP_TYPE_STRUCT => {
+                block_size = Some(self.size(ty[1], None)?);
+                if storage == STORAGE_STORAGE_BUFFER || self.decoration(ty[1], DECORATION_BUFFER_BLOCK).is_some() {
+                    DescriptorType::StorageBuffer
+                } else {
+                    DescriptorType::UniformBuffer
+                }
+            },
+            OP_TYPE_SAMPLED_IMAGE => DescriptorType::CombinedImageSampler,
+            OP_TYPE_SAMPLER => DescriptorType::Sampler,
+            OP_TYPE_IMAGE => match (operand(3), operand(7) == 1) {
+                (DIM_SUBPASS_DATA, _) => DescriptorType::InputAttachment,
+                (DIM_BUFFER, true) => DescriptorType::UniformTexelBuffer,
+                (DIM_BUFFER, false) => DescriptorType::StorageTexelBuffer,
+                (_, true) => DescriptorType::SampledImage,
+                (_, false) => DescriptorType::StorageImage,
+            },
+            _ => return Err(format!("type {} can't be a descriptor", ty[1])),
+        };
+        Ok(Descriptor { ty: descriptor_type, array_count, block_size })
+    }
+
+    //Bytes a type takes in a buffer block, from the explicit layout decorations. A matrix gets its stride
+    //from the struct member it's in.
+    fn size(&self, id: u32, matrix_stride: Option<u32>) -> Result<u32, String> {
+        let ty = self.ty(id)?;
+        let operand = |i: usize| operand(ty, i);
+        match ty[0] & 0xffff {
+            OP_TYPE_INT | OP_TYPE_FLOAT => Ok(operand(2) / 8),
+            OP_TYPE_VECTOR => Ok(self.size(operand(2), None)? * operand(3)),
+            OP_TYPE_MATRIX => matrix_stride.map(|stride| stride * operand(3))
+                .ok_or_else(|| format!("matrix {} has no stride", id)),
+            OP_TYPE_ARRAY => {
+                let stride = self.decoration(id, DECORATION_ARRAY_STRIDE)
+                    .ok_or_else(|| format!("array {} has no stride", id))?;
+                Ok(stride * self.array_length(operand(3))?)
+            },
+            OP_TYPE_STRUCT => {
+                let mut size = 0;
+                for (member, &member_ty) in ty[2..].iter().enumerate() {
+                    let member = member as u32;
+                    let offset = self.member_decoration(id, member, DECORATION_OFFSET)
+                        .ok_or_else(|| format!("member {} of struct {} has no offset", member, id))?;
+                    let stride = self.member_decoration(id, member, DECORATION_MATRIX_STRIDE);
+                    size = size.max(offset + self.size(member_ty, stride)?);
+                }
+                Ok(size)
+            },
+            _ => Err(format!("type {} has no size in a buffer", id)),
+        }
+    }
+}
+
+//Missing operands read as 0 instead of panicking on a malformed module
+fn operand(instruction: &[u32], i: usize) -> u32 {
+    instruction.get(i).cloned().unwrap_or(0)
+}
+
+//A literal string operand, nul terminated and padded to whole words
+fn string(words: &[u32]) -> String {
+    let bytes: Vec<u8> = words.iter()
+        .flat_map(|word| (0..4).map(move |byte| (word >> (byte * 8)) as u8))
+        .take_while(|&byte| byte != 0)
+        .collect();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+fn descriptor_type(ty: &DescriptorDescTy) -> DescriptorType {
+    match *ty {
+        DescriptorDescTy::Sampler => DescriptorType::Sampler,
+        DescriptorDescTy::CombinedImageSampler(_) => DescriptorType::CombinedImageSampler,
+        DescriptorDescTy::Image(ref desc) if desc.sampled => DescriptorType::SampledImage,
+        DescriptorDescTy::Image(_) => DescriptorType::StorageImage,
+        DescriptorDescTy::TexelBuffer { storage: true, .. } => DescriptorType::StorageTexelBuffer,
+        DescriptorDescTy::TexelBuffer { .. } => DescriptorType::UniformTexelBuffer,
+        DescriptorDescTy::InputAttachment { .. } => DescriptorType::InputAttachment,
+        DescriptorDescTy::Buffer(ref desc) if desc.storage => DescriptorType::StorageBuffer,
+        DescriptorDescTy::Buffer(_) => DescriptorType::UniformBuffer,
+    }
+}
+
+fn variables(variables: &[Variable]) -> String {
+    let described: Vec<String> = variables.iter()
+        .map(|variable| format!("{:?} at {}", variable.format, variable.location.start))
+        .collect();
+    format!("[{}]", described.join(", "))
+}
+
+fn describe(descriptor: &Descriptor) -> String {
+    let mut description = format!("{:?}", descriptor.ty);
+    if descriptor.array_count != 1 {
+        description += &format!(" array of {}", descriptor.array_count);
+    }
+    if let Some(size) = descriptor.block_size {
+        description += &format!(" of {} bytes", size);
+    }
+    description
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //Assembles a module from instructions given as opcode and operands
+    fn module(instructions: &[(u32, Vec<u32>)]) -> Vec<u32> {
+        let mut words = vec![MAGIC, 0x0001_0000, 0, 100, 0];
+        for &(opcode, ref operands) in instructions {
+            words.push(((operands.len() as u32 + 1) << 16) | opcode);
+            words.extend(operands);
+        }
+        words
+    }
+
+    //"main" as a nul terminated literal string
+    const MAIN: [u32; 2] = [0x6e69_616d, 0];
+
+    //A vertex shader with a vec3 and a mat4 input, a vec2 output next to gl_PerVertex, a uniform block with a
+    //mat4 and a float array, and an array of two combined image samplers
+    fn vertex_module() -> Vec<(u32, Vec<u32>)> {
+        vec![
+            (OP_ENTRY_POINT, [vec![0, 1], MAIN.to_vec(), vec![20, 21, 22, 23]].concat()),
+            (OP_DECORATE, vec![20, DECORATION_LOCATION, 0]),
+            (OP_DECORATE, vec![21, DECORATION_LOCATION, 1]),
+            (OP_DECORATE, vec![22, DECORATION_LOCATION, 0]),
+            (OP_MEMBER_DECORATE, vec![8, 0, DECORATION_BUILT_IN, 0]),
+            (OP_DECORATE, vec![6, DECORATION_ARRAY_STRIDE, 16]),
+            (OP_MEMBER_DECORATE, vec![9, 0, DECORATION_OFFSET, 0]),
+            (OP_MEMBER_DECORATE, vec![9, 0, DECORATION_MATRIX_STRIDE, 16]),
+            (OP_MEMBER_DECORATE, vec![9, 1, DECORATION_OFFSET, 64]),
+            (OP_DECORATE, vec![24, DECORATION_DESCRIPTOR_SET, 0]),
+            (OP_DECORATE, vec![24, DECORATION_BINDING, 0]),
+            (OP_DECORATE, vec![25, DECORATION_DESCRIPTOR_SET, 1]),
+            (OP_DECORATE, vec![25, DECORATION_BINDING, 2]),
+            (OP_TYPE_FLOAT, vec![2, 32]),
+            (OP_TYPE_VECTOR, vec![3, 2, 3]),
+            (OP_TYPE_VECTOR, vec![4, 2, 4]),
+            (OP_TYPE_MATRIX, vec![5, 4, 4]),
+            (OP_TYPE_INT, vec![10, 32, 0]),
+            (OP_CONSTANT, vec![10, 11, 3]),
+            (OP_CONSTANT, vec![10, 12, 2]),
+            (OP_TYPE_ARRAY, vec![6, 2, 11]),
+            (OP_TYPE_VECTOR, vec![7, 2, 2]),
+            (OP_TYPE_STRUCT, vec![8, 4]),
+            (OP_TYPE_STRUCT, vec![9, 5, 6]),
+            (OP_TYPE_IMAGE, vec![13, 2, 1, 0, 0, 0, 1, 0]),
+            (OP_TYPE_SAMPLED_IMAGE, vec![14, 13]),
+            (OP_TYPE_ARRAY, vec![15, 14, 12]),
+            (OP_TYPE_POINTER, vec![30, STORAGE_INPUT, 3]),
+            (OP_TYPE_POINTER, vec![31, STORAGE_INPUT, 5]),
+            (OP_TYPE_POINTER, vec![32, STORAGE_OUTPUT, 7]),
+            (OP_TYPE_POINTER, vec![33, STORAGE_OUTPUT, 8]),
+            (OP_TYPE_POINTER, vec![34, STORAGE_UNIFORM, 9]),
+            (OP_TYPE_POINTER, vec![35, STORAGE_UNIFORM_CONSTANT, 15]),
+            (OP_VARIABLE, vec![30, 20, STORAGE_INPUT]),
+            (OP_VARIABLE, vec![31, 21, STORAGE_INPUT]),
+            (OP_VARIABLE, vec![32, 22, STORAGE_OUTPUT]),
+            (OP_VARIABLE, vec![33, 23, STORAGE_OUTPUT]),
+            (OP_VARIABLE, vec![34, 24, STORAGE_UNIFORM]),
+            (OP_VARIABLE, vec![35, 25, STORAGE_UNIFORM_CONSTANT]),
+        ]
+    }
+
+    fn vertex_interface() -> Interface {
+        let mut descriptors = BTreeMap::new();
+        descriptors.insert((0, 0), Descriptor { ty: DescriptorType::UniformBuffer, array_count: 1,
+            block_size: Some(112) });
+        descriptors.insert((1, 2), Descriptor { ty: DescriptorType::CombinedImageSampler, array_count: 2,
+            block_size: None });
+        Interface {
+            inputs: vec![
+                Variable { location: 0..1, format: Format::R32G32B32Sfloat },
+                Variable { location: 1..5, format: Format::R32G32B32A32Sfloat },
+            ],
+            outputs: vec![Variable { location: 0..1, format: Format::R32G32Sfloat }],
+            descriptors,
+            push_constants: None,
+        }
+    }
+
+    #[test]
+    fn reads_variables_and_descriptors() {
+        let interface = reflect(&module(&vertex_module()), Stage::Vertex).unwrap();
+        assert_eq!(interface, vertex_interface());
+    }
+
+    #[test]
+    fn needs_a_main_entry_point_for_the_stage() {
+        assert!(reflect(&module(&vertex_module()), Stage::Fragment).is_err());
+        assert!(reflect(&[0; 5], Stage::Vertex).is_err());
+        assert!(reflect(&module(&vertex_module())[..8], Stage::Vertex).is_err());
+    }
+
+    #[test]
+    fn same_interface_passes() {
+        assert_eq!(vertex_interface().check(&vertex_interface()), Ok(()));
+    }
+
+    #[test]
+    fn changed_inputs_and_outputs_are_rejected() {
+        let mut changed = vertex_interface();
+        changed.inputs[0].format = Format::R32G32B32A32Sfloat;
+        assert!(changed.check(&vertex_interface()).unwrap_err().starts_with("inputs changed"));
+
+        let mut moved = vertex_interface();
+        moved.outputs[0].location = 1..2;
+        assert!(moved.check(&vertex_interface()).unwrap_err().starts_with("outputs changed"));
+
+        let mut removed = vertex_interface();
+        removed.inputs.pop();
+        assert!(removed.check(&vertex_interface()).is_err());
+    }
+
+    #[test]
+    fn changed_or_new_descriptors_are_rejected() {
+        let mut grown = vertex_interface();
+        grown.descriptors.get_mut(&(0, 0)).unwrap().block_size = Some(128);
+        assert_eq!(grown.check(&vertex_interface()),
+            Err("set 0 binding 0 changed from UniformBuffer of 112 bytes to UniformBuffer of 128 bytes".to_string()));
+
+        let mut retyped = vertex_interface();
+        retyped.descriptors.get_mut(&(1, 2)).unwrap().ty = DescriptorType::SampledImage;
+        assert!(retyped.check(&vertex_interface()).is_err());
+
+        let mut added = vertex_interface();
+        added.descriptors.insert((2, 0), Descriptor { ty: DescriptorType::Sampler, array_count: 1, block_size: None });
+        assert!(added.check(&vertex_interface()).unwrap_err().starts_with("set 2 binding 0 is new"));
+
+        let mut pushed = vertex_interface();
+        pushed.push_constants = Some(16);
+        assert!(pushed.check(&vertex_interface()).is_err());
+    }
+
+    #[test]
+    fn unused_descriptors_can_be_dropped() {
+        let mut fewer = vertex_interface();
+        fewer.descriptors.remove(&(1, 2));
+        assert_eq!(fewer.check(&vertex_interface()), Ok(()));
+    }
+}
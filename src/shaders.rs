@@ -0,0 +1,84 @@
+use super::{frag, vertex};
+#[cfg(feature = "hot-reload")]
+use super::reflect::{self, Interface, Stage};
+use std::ffi::CStr;
+#[cfg(feature = "hot-reload")]
+use std::mem;
+use std::sync::Arc;
+use vulkano::OomError;
+use vulkano::descriptor::descriptor::ShaderStages;
+use vulkano::device::Device;
+use vulkano::pipeline::shader::{GraphicsEntryPoint, GraphicsShaderType, ShaderModule};
+
+const ENTRY_POINT: &[u8] = b"main\0";
+
+//The vertex and fragment shader modules the pipelines are built from. The entry points describe the
+//inputs, outputs and descriptors with the types vulkano_shaders generated for the compiled in shaders,
+//so other modules have to keep that interface.
+pub struct Shaders {
+    vertex: Arc<ShaderModule>,
+    fragment: Arc<ShaderModule>,
+}
+
+impl Shaders {
+    //vertex.glsl and frag.glsl as they were when the crate was built
+    pub fn builtin(device: Arc<Device>) -> Result<Shaders, OomError> {
+        Ok(Shaders {
+            vertex: vertex::Shader::load(device.clone())?.module().clone(),
+            fragment: frag::Shader::load(device)?.module().clone(),
+        })
+    }
+
+    //Both have to be valid SPIR-V. Modules whose inputs, outputs or descriptors differ from the compiled in
+    //shaders are rejected, the pipelines, vertex buffers and descriptor sets are built for that interface.
+    #[cfg(feature = "hot-reload")]
+    pub unsafe fn from_words(device: Arc<Device>, vertex: &[u32], fragment: &[u32]) -> Result<Shaders, String> {
+        reflect::reflect(vertex, Stage::Vertex)
+            .and_then(|interface| interface.check(&vertex_interface()))
+            .map_err(|err| format!("the vertex shader doesn't fit the pipeline, {}", err))?;
+        reflect::reflect(fragment, Stage::Fragment)
+            .and_then(|interface| interface.check(&fragment_interface()))
+            .map_err(|err| format!("the fragment shader doesn't fit the pipeline, {}", err))?;
+
+        let module = |words| ShaderModule::from_words(device.clone(), words)
+            .map_err(|err| format!("could not create the shader modules: {}", err));
+        Ok(Shaders {
+            vertex: module(vertex)?,
+            fragment: module(fragment)?,
+        })
+    }
+
+    pub fn vertex_entry_point(&self) -> GraphicsEntryPoint<'_, (), vertex::MainInput, vertex::MainOutput, vertex::Layout> {
+        let stages = ShaderStages { vertex: true, ..ShaderStages::none() };
+        unsafe {
+            self.vertex.graphics_entry_point(entry_point_name(), vertex::MainInput, vertex::MainOutput,
+                vertex::Layout(stages), GraphicsShaderType::Vertex)
+        }
+    }
+
+    pub fn fragment_entry_point(&self) -> GraphicsEntryPoint<'_, (), frag::MainInput, frag::MainOutput, frag::Layout> {
+        let stages = ShaderStages { fragment: true, ..ShaderStages::none() };
+        unsafe {
+            self.fragment.graphics_entry_point(entry_point_name(), frag::MainInput, frag::MainOutput,
+                frag::Layout(stages), GraphicsShaderType::Fragment)
+        }
+    }
+}
+
+#[cfg(feature = "hot-reload")]
+fn vertex_interface() -> Interface {
+    let stages = ShaderStages { vertex: true, ..ShaderStages::none() };
+    Interface::generated(vertex::MainInput, vertex::MainOutput, vertex::Layout(stages),
+        &[((0, 0), mem::size_of::<vertex::ty::Data>())])
+}
+
+#[cfg(feature = "hot-reload")]
+fn fragment_interface() -> Interface {
+    let stages = ShaderStages { fragment: true, ..ShaderStages::none() };
+    Interface::generated(frag::MainInput, frag::MainOutput, frag::Layout(stages),
+        &[((0, 1), mem::size_of::<frag::ty::Lights>()), ((1, 0), mem::size_of::<frag::ty::Material>())])
+}
+
+fn entry_point_name() -> &'static CStr {
+    CStr::from_bytes_with_nul(ENTRY_POINT).expect("Entry point name is not nul terminated")
+}
@@ -0,0 +1,179 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+//About two seconds at 60 fps, long enough to smooth out single hitches without hiding trends
+const WINDOW: usize = 120;
+
+//Frame pacing over the last WINDOW frames, see Renderer::frame_stats
+#[derive(Clone, Debug, Default)]
+pub struct FrameStats {
+    //Time between consecutive submissions, newest last
+    intervals: VecDeque<Duration>,
+    //Time spent blocked on the fence of the frame slot about to be reused, newest last
+    waits: VecDeque<Duration>,
+    last_submit: Option<Instant>,
+}
+
+impl FrameStats {
+    pub fn new() -> FrameStats {
+        FrameStats::default()
+    }
+
+    //Called once a frame has been submitted, with how long the CPU waited for its slot
+    pub fn record(&mut self, waited: Duration) {
+        let now = Instant::now();
+        if let Some(last) = self.last_submit {
+            push(&mut self.intervals, now.duration_since(last));
+        }
+        push(&mut self.waits, waited);
+        self.last_submit = Some(now);
+    }
+
+    //Number of intervals the statistics are computed from
+    pub fn frames(&self) -> usize {
+        self.intervals.len()
+    }
+
+    pub fn average_frame_time(&self) -> Option<Duration> {
+        average(&self.intervals)
+    }
+
+    pub fn worst_frame_time(&self) -> Option<Duration> {
+        self.intervals.iter().max().cloned()
+    }
+
+    //Standard deviation of the frame time. Steady pacing keeps it near zero even at a low frame rate.
+    pub fn jitter(&self) -> Option<Duration> {
+        let mean = millis(self.average_frame_time()?);
+        let variance = self.intervals.iter().map(|&interval| (millis(interval) - mean).powi(2)).sum::<f32>()
+            / self.intervals.len() as f32;
+        Some(from_millis(variance.sqrt()))
+    }
+
+    pub fn fps(&self) -> Option<f32> {
+        self.average_frame_time().map(millis).filter(|&ms| ms > 0.0).map(|ms| 1000.0 / ms)
+    }
+
+    //Time the CPU spent waiting for the GPU to release a frame. Close to the frame time means the GPU
+    //(or vsync) is the bottleneck, close to zero means the CPU is.
+    pub fn average_wait(&self) -> Option<Duration> {
+        average(&self.waits)
+    }
+}
+
+impl fmt::Display for FrameStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.fps(), self.average_frame_time(), self.worst_frame_time(), self.jitter(), self.average_wait()) {
+            (Some(fps), Some(average), Some(worst), Some(jitter), Some(wait)) =>
+                write!(f, "{:.1} fps, {:.2} ms average, {:.2} ms worst, {:.2} ms jitter, {:.2} ms waiting on the GPU",
+                    fps, millis(average), millis(worst), millis(jitter), millis(wait)),
+            _ => write!(f, "no frames yet"),
+        }
+    }
+}
+
+fn push(samples: &mut VecDeque<Duration>, sample: Duration) {
+    if samples.len() == WINDOW {
+        samples.pop_front();
+    }
+    samples.push_back(sample);
+}
+
+fn average(samples: &VecDeque<Duration>) -> Option<Duration> {
+    if samples.is_empty() {
+        return None;
+    }
+    Some(samples.iter().sum::<Duration>() / samples.len() as u32)
+}
+
+fn millis(duration: Duration) -> f32 {
+    duration.as_secs() as f32 * 1000.0 + duration.subsec_nanos() as f32 / 1_000_000.0
+}
+
+fn from_millis(ms: f32) -> Duration {
+    Duration::from_nanos((ms * 1_000_000.0) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //Stats over the given frame intervals and waits, in milliseconds
+    fn stats(intervals: &[u64], waits: &[u64]) -> FrameStats {
+        let mut stats = FrameStats::new();
+        for &ms in intervals {
+            push(&mut stats.intervals, Duration::from_millis(ms));
+        }
+        for &ms in waits {
+            push(&mut stats.waits, Duration::from_millis(ms));
+        }
+        stats
+    }
+
+    fn assert_close(actual: Duration, expected_ms: f32) {
+        assert!((millis(actual) - expected_ms).abs() < 1e-3, "{:?} is not {} ms", actual, expected_ms);
+    }
+
+    #[test]
+    fn no_frames_have_no_statistics() {
+        let stats = FrameStats::new();
+        assert_eq!(stats.frames(), 0);
+        assert_eq!(stats.average_frame_time(), None);
+        assert_eq!(stats.worst_frame_time(), None);
+        assert_eq!(stats.jitter(), None);
+        assert_eq!(stats.fps(), None);
+        assert_eq!(stats.average_wait(), None);
+        assert_eq!(stats.to_string(), "no frames yet");
+    }
+
+    #[test]
+    fn statistics_of_known_intervals() {
+        let stats = stats(&[10, 20, 30], &[2, 4]);
+        assert_eq!(stats.frames(), 3);
+        assert_eq!(stats.average_frame_time(), Some(Duration::from_millis(20)));
+        assert_eq!(stats.worst_frame_time(), Some(Duration::from_millis(30)));
+        //sqrt((10² + 0² + 10²) / 3)
+        assert_close(stats.jitter().unwrap(), (200.0f32 / 3.0).sqrt());
+        assert!((stats.fps().unwrap() - 50.0).abs() < 1e-3);
+        assert_eq!(stats.average_wait(), Some(Duration::from_millis(3)));
+        assert_eq!(stats.to_string(),
+            "50.0 fps, 20.00 ms average, 30.00 ms worst, 8.16 ms jitter, 3.00 ms waiting on the GPU");
+    }
+
+    #[test]
+    fn steady_pacing_has_no_jitter() {
+        let stats = stats(&[40; 10], &[0; 10]);
+        assert_close(stats.jitter().unwrap(), 0.0);
+        assert!((stats.fps().unwrap() - 25.0).abs() < 1e-3);
+        assert_eq!(stats.average_wait(), Some(Duration::from_millis(0)));
+    }
+
+    #[test]
+    fn zero_frame_times_have_no_frame_rate() {
+        let stats = stats(&[0, 0], &[]);
+        assert_eq!(stats.average_frame_time(), Some(Duration::from_millis(0)));
+        assert_eq!(stats.fps(), None);
+    }
+
+    #[test]
+    fn only_the_last_window_counts() {
+        let mut stats = stats(&[100], &[]);
+        for _ in 0..WINDOW {
+            push(&mut stats.intervals, Duration::from_millis(10));
+        }
+        assert_eq!(stats.frames(), WINDOW);
+        assert_eq!(stats.worst_frame_time(), Some(Duration::from_millis(10)));
+    }
+
+    #[test]
+    fn the_first_submission_has_no_interval() {
+        let mut stats = FrameStats::new();
+        stats.record(Duration::from_millis(5));
+        assert_eq!(stats.frames(), 0);
+        assert_eq!(stats.average_wait(), Some(Duration::from_millis(5)));
+        stats.record(Duration::from_millis(1));
+        assert_eq!(stats.frames(), 1);
+        assert_eq!(stats.average_wait(), Some(Duration::from_millis(3)));
+    }
+}
@@ -0,0 +1,215 @@
+use image::{ImageError, ImageFormat, ImageResult, Rgba, RgbaImage};
+use std::cmp::{max, min};
+use std::fs;
+use std::path::Path;
+use vulkano::sampler::{BorderColor, Filter, MipmapMode, SamplerAddressMode};
+
+//Magic bytes first, then the file extension, which is the only way to recognize TGA files
+pub fn image_format(bytes: &[u8], path: Option<&Path>) -> ImageResult<ImageFormat> {
+    if let Ok(format) = image::guess_format(bytes) {
+        return Ok(format);
+    }
+
+    let extension = path.and_then(|path| path.extension())
+        .and_then(|extension| extension.to_str())
+        .map_or(String::new(), |extension| extension.to_ascii_lowercase());
+
+    match extension.as_str() {
+        "jpg" | "jpeg" => Ok(ImageFormat::JPEG),
+        "png" => Ok(ImageFormat::PNG),
+        "gif" => Ok(ImageFormat::GIF),
+        "webp" => Ok(ImageFormat::WEBP),
+        "tif" | "tiff" => Ok(ImageFormat::TIFF),
+        "tga" => Ok(ImageFormat::TGA),
+        "bmp" => Ok(ImageFormat::BMP),
+        "ico" => Ok(ImageFormat::ICO),
+        "hdr" => Ok(ImageFormat::HDR),
+        "pbm" | "pam" | "ppm" | "pgm" => Ok(ImageFormat::PNM),
+        _ => Err(ImageError::UnsupportedError("Unrecognized image format".to_string())),
+    }
+}
+
+//The path is only a hint for formats without magic bytes
+pub fn decode(bytes: &[u8], path: Option<&Path>) -> ImageResult<RgbaImage> {
+    let format = image_format(bytes, path)?;
+    Ok(image::load_from_memory_with_format(bytes, format)?.to_rgba())
+}
+
+pub fn load_file(path: &Path) -> ImageResult<RgbaImage> {
+    let bytes = fs::read(path)?;
+    decode(&bytes, Some(path))
+}
+
+//Number of levels in a full mip chain down to 1x1. vulkano's MipmapsCount::Log2 rounds up and asks for
+//one level too many on textures that aren't a power of two.
+pub fn mip_levels(width: u32, height: u32) -> u32 {
+    32 - max(max(width, height), 1).leading_zeros()
+}
+
+pub fn mip_dimensions(width: u32, height: u32, level: u32) -> (u32, u32) {
+    (max(width >> level, 1), max(height >> level, 1))
+}
+
+//Every level of the mip chain, starting with the image itself. Each level is a 2x2 box filter of the one
+//before it, averaged in linear space for sRGB images. Used when the levels can't be blitted on the GPU.
+pub fn mip_chain(image: &RgbaImage, srgb: bool) -> Vec<RgbaImage> {
+    let (width, height) = image.dimensions();
+    let mut chain = vec![image.clone()];
+
+    for level in 1..mip_levels(width, height) {
+        let (w, h) = mip_dimensions(width, height, level);
+        let next = {
+            let previous = &chain[chain.len() - 1];
+            let (pw, ph) = previous.dimensions();
+            RgbaImage::from_fn(w, h, |x, y| {
+                let mut sum = [0.0; 4];
+                for &(sx, sy) in &[(2 * x, 2 * y), (2 * x + 1, 2 * y), (2 * x, 2 * y + 1), (2 * x + 1, 2 * y + 1)] {
+                    let pixel = previous.get_pixel(min(sx, pw - 1), min(sy, ph - 1)).data;
+                    for c in 0..4 {
+                        let value = pixel[c] as f32 / 255.0;
+                        sum[c] += if srgb && c < 3 { srgb_to_linear(value) } else { value };
+                    }
+                }
+                let mut pixel = [0; 4];
+                for c in 0..4 {
+                    let value = sum[c] / 4.0;
+                    let value = if srgb && c < 3 { linear_to_srgb(value) } else { value };
+                    pixel[c] = (value * 255.0).round() as u8;
+                }
+                Rgba(pixel)
+            })
+        };
+        chain.push(next);
+    }
+
+    chain
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SamplerOptions {
+    //Used for magnification, minification and between mip levels
+    pub filter: Filter,
+    //1.0 turns anisotropic filtering off, larger values are clamped to what the device supports
+    pub anisotropy: f32,
+    pub address_mode: SamplerAddressMode,
+}
+
+impl Default for SamplerOptions {
+    fn default() -> Self {
+        SamplerOptions {
+            filter: Filter::Linear,
+            anisotropy: 16.0,
+            address_mode: SamplerAddressMode::Repeat,
+        }
+    }
+}
+
+impl SamplerOptions {
+    pub fn mipmap_mode(&self) -> MipmapMode {
+        match self.filter {
+            Filter::Nearest => MipmapMode::Nearest,
+            Filter::Linear => MipmapMode::Linear,
+        }
+    }
+}
+
+pub fn parse_filter(name: &str) -> Option<Filter> {
+    match name {
+        "nearest" => Some(Filter::Nearest),
+        "linear" => Some(Filter::Linear),
+        _ => None,
+    }
+}
+
+pub fn parse_address_mode(name: &str) -> Option<SamplerAddressMode> {
+    match name {
+        "repeat" => Some(SamplerAddressMode::Repeat),
+        "mirror" => Some(SamplerAddressMode::MirroredRepeat),
+        "clamp" => Some(SamplerAddressMode::ClampToEdge),
+        "border" => Some(SamplerAddressMode::ClampToBorder(BorderColor::FloatTransparentBlack)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checker(width: u32, height: u32) -> RgbaImage {
+        RgbaImage::from_fn(width, height, |x, y| {
+            if (x + y) % 2 == 0 { Rgba([0, 0, 0, 255]) } else { Rgba([255, 255, 255, 255]) }
+        })
+    }
+
+    #[test]
+    fn mip_levels_go_down_to_one_pixel() {
+        assert_eq!(mip_levels(1, 1), 1);
+        assert_eq!(mip_levels(2, 2), 2);
+        assert_eq!(mip_levels(256, 256), 9);
+        assert_eq!(mip_levels(256, 1), 9);
+    }
+
+    #[test]
+    fn mip_levels_round_down_for_npot_sizes() {
+        assert_eq!(mip_levels(5, 3), 3);
+        assert_eq!(mip_levels(3, 5), 3);
+        assert_eq!(mip_levels(1000, 600), 10);
+        assert_eq!(mip_levels(0, 0), 1);
+    }
+
+    #[test]
+    fn last_mip_is_one_pixel() {
+        for &(width, height) in &[(5, 3), (1000, 600), (256, 1), (1, 7)] {
+            assert_eq!(mip_dimensions(width, height, mip_levels(width, height) - 1), (1, 1));
+        }
+        assert_eq!(mip_dimensions(5, 3, 1), (2, 1));
+        assert_eq!(mip_dimensions(5, 3, 0), (5, 3));
+    }
+
+    #[test]
+    fn mip_chain_halves_down_to_one_pixel() {
+        let chain = mip_chain(&checker(5, 3), false);
+        let sizes = chain.iter().map(|level| level.dimensions()).collect::<Vec<_>>();
+        assert_eq!(sizes, vec![(5, 3), (2, 1), (1, 1)]);
+    }
+
+    #[test]
+    fn srgb_checker_averages_in_linear_space() {
+        let chain = mip_chain(&checker(2, 2), true);
+        assert_eq!(chain.len(), 2);
+        let pixel = chain[1].get_pixel(0, 0).data;
+        //Half the light of white is 188 in sRGB, not the 128 a naive average gives
+        for &channel in &pixel[..3] {
+            assert!((187..=189).contains(&channel), "{} is not about 188", channel);
+        }
+        assert_eq!(pixel[3], 255);
+    }
+
+    #[test]
+    fn linear_checker_averages_directly() {
+        let pixel = mip_chain(&checker(2, 2), false)[1].get_pixel(0, 0).data;
+        assert_eq!(pixel, [128, 128, 128, 255]);
+    }
+
+    #[test]
+    fn alpha_is_never_treated_as_srgb() {
+        let image = RgbaImage::from_fn(2, 1, |x, _| Rgba([255, 255, 255, if x == 0 { 0 } else { 255 }]));
+        assert_eq!(mip_chain(&image, true)[1].get_pixel(0, 0).data[3], 128);
+    }
+}
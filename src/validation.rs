@@ -0,0 +1,144 @@
+use std::env;
+use std::sync::{Arc, Mutex};
+use vulkano::instance::debug::{DebugCallback, Message, MessageTypes};
+use vulkano::instance::{layers_list, Instance, InstanceCreationError, InstanceExtensions};
+
+//0 turns validation off, 1 turns it on, panic also panics on validation errors
+pub const VALIDATION_ENV_VAR: &str = "RENDERERVK_VALIDATION";
+
+//The first one installed is used. The LunarG meta layer is what SDKs before 1.1.106 ship.
+const VALIDATION_LAYERS: [&str; 2] = ["VK_LAYER_KHRONOS_validation", "VK_LAYER_LUNARG_standard_validation"];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ValidationOptions {
+    pub enabled: bool,
+    //Validation errors are reported from inside the driver, where vulkano swallows panics. They are
+    //collected instead, and the next call to Validation::check panics.
+    pub panic_on_error: bool,
+}
+
+impl Default for ValidationOptions {
+    //On in debug builds, unless the environment variable says otherwise
+    fn default() -> Self {
+        let debug_build = ValidationOptions { enabled: cfg!(debug_assertions), panic_on_error: false };
+        ValidationOptions::from_env().unwrap_or(debug_build)
+    }
+}
+
+impl ValidationOptions {
+    pub fn parse(value: &str) -> Option<ValidationOptions> {
+        match value {
+            "0" => Some(ValidationOptions { enabled: false, panic_on_error: false }),
+            "1" => Some(ValidationOptions { enabled: true, panic_on_error: false }),
+            "panic" => Some(ValidationOptions { enabled: true, panic_on_error: true }),
+            _ => None,
+        }
+    }
+
+    pub fn from_env() -> Option<ValidationOptions> {
+        let value = env::var(VALIDATION_ENV_VAR).ok()?;
+        let options = ValidationOptions::parse(&value);
+        if options.is_none() {
+            warn!("Ignoring {}={}, expected 0, 1 or panic", VALIDATION_ENV_VAR, value);
+        }
+        options
+    }
+}
+
+//Keeps the debug callback registered for as long as it lives
+pub struct Validation {
+    _callback: Option<DebugCallback>,
+    errors: Arc<Mutex<Vec<String>>>,
+    panic_on_error: bool,
+}
+
+impl Validation {
+    pub fn disabled() -> Validation {
+        Validation { _callback: None, errors: Arc::new(Mutex::new(Vec::new())), panic_on_error: false }
+    }
+
+    //Panics with the errors reported since the last check, when panic_on_error is set
+    pub fn check(&self) {
+        let mut errors = self.errors.lock().expect("Validation errors poisoned");
+        if self.panic_on_error && !errors.is_empty() {
+            let message = errors.join("\n");
+            errors.clear();
+            panic!("Vulkan validation failed:\n{}", message);
+        }
+    }
+}
+
+//The installed validation layer, if any
+pub fn validation_layer() -> Option<&'static str> {
+    let installed: Vec<String> = match layers_list() {
+        Ok(layers) => layers.map(|layer| layer.name().to_string()).collect(),
+        Err(_) => return None,
+    };
+    VALIDATION_LAYERS.iter().cloned().find(|name| installed.iter().any(|layer| layer == name))
+}
+
+//Creates the instance with the validation layer and debug callback when they are enabled and installed.
+//Missing pieces are logged and skipped rather than failing, since validation is only a development aid.
+pub fn create_instance(extensions: &InstanceExtensions, options: &ValidationOptions)
+    -> Result<(Arc<Instance>, Validation), InstanceCreationError> {
+
+    let info = app_info_from_cargo_toml!();
+    if !options.enabled {
+        return Ok((Instance::new(Some(&info), extensions, None)?, Validation::disabled()));
+    }
+
+    let layer = validation_layer();
+    if layer.is_none() {
+        warn!("Validation requested but none of {:?} are installed", VALIDATION_LAYERS);
+    }
+    let debug_report = InstanceExtensions::supported_by_core().map_or(false, |supported| supported.ext_debug_report);
+    if !debug_report {
+        warn!("Validation requested but VK_EXT_debug_report is not supported, messages won't be shown");
+    }
+
+    let extensions = InstanceExtensions { ext_debug_report: debug_report, ..*extensions };
+    let instance = Instance::new(Some(&info), &extensions, layer)?;
+
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let callback = if debug_report {
+        let reported = errors.clone();
+        let panic_on_error = options.panic_on_error;
+        let callback = DebugCallback::new(&instance, MessageTypes {
+            error: true,
+            warning: true,
+            performance_warning: true,
+            information: true,
+            debug: true,
+        }, move |message| {
+            log_message(message);
+            if panic_on_error && message.ty.error {
+                reported.lock().expect("Validation errors poisoned")
+                    .push(format!("[{}] {}", message.layer_prefix, message.description));
+            }
+        });
+        match callback {
+            Ok(callback) => Some(callback),
+            Err(err) => {
+                warn!("Could not register the debug callback: {}", err);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    Ok((instance, Validation { _callback: callback, errors, panic_on_error: options.panic_on_error }))
+}
+
+//The most severe flag decides the log level
+fn log_message(message: &Message) {
+    if message.ty.error {
+        error!("[{}] {}", message.layer_prefix, message.description);
+    } else if message.ty.warning || message.ty.performance_warning {
+        warn!("[{}] {}", message.layer_prefix, message.description);
+    } else if message.ty.information {
+        info!("[{}] {}", message.layer_prefix, message.description);
+    } else {
+        debug!("[{}] {}", message.layer_prefix, message.description);
+    }
+}
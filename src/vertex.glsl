@@ -1,13 +1,29 @@
 #version 450
 
 layout(location = 0) in vec3 position;
-//layout(location = 0) out vec2 tex_coords;
 layout(location = 1) in vec3 normal;
+layout(location = 2) in vec2 tex_coord;
 layout(location = 0) out vec3 v_normal;
+layout(location = 1) out vec2 v_tex_coord;
+layout(location = 2) out vec3 v_position;
+layout(location = 3) out vec3 v_eye;
 
+layout(set = 0, binding = 0) uniform Data {
+    mat4 model;
+    mat4 view;
+    mat4 proj;
+    //Inverse transpose of the model matrix, keeps normals perpendicular under non-uniform scaling
+    mat4 normal_matrix;
+} uniforms;
 
 void main() {
-    gl_Position = vec4(position, 1.0);
-    v_normal = normal;
-    //tex_coords = position.xy + vec2(0.5);
+    vec4 world_position = uniforms.model * vec4(position, 1.0);
+    gl_Position = uniforms.proj * uniforms.view * world_position;
+    v_normal = mat3(uniforms.normal_matrix) * normal;
+    v_tex_coord = tex_coord;
+    v_position = world_position.xyz;
+    //Camera position in world space, the view matrix is a rotation followed by a translation
+    v_eye = -transpose(mat3(uniforms.view)) * uniforms.view[3].xyz;
+    //Only read by the point list pipeline
+    gl_PointSize = 3.0;
 }
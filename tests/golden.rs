@@ -0,0 +1,25 @@
+use renderervk::{golden, gpu};
+use renderervk::validation::ValidationOptions;
+use std::path::Path;
+
+//Renders the reference scenes and compares them to the checked in images. Machines without a Vulkan
+//driver or device, like most CI containers, skip it. RENDERERVK_GPU and RENDERERVK_VALIDATION apply.
+#[test]
+fn golden_images() {
+    match gpu::list_gpus() {
+        Ok(ref gpus) if !gpus.is_empty() => (),
+        Ok(_) => {
+            eprintln!("Skipping the golden images, there is no Vulkan device");
+            return;
+        },
+        Err(err) => {
+            eprintln!("Skipping the golden images, Vulkan is not available: {}", err);
+            return;
+        },
+    }
+
+    let directory = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden"));
+    let passed = golden::run(directory, false, golden::DEFAULT_TOLERANCE, None, &ValidationOptions::default())
+        .expect("Could not render the golden scenes");
+    assert!(passed, "Some scenes don't match their golden images in {}", directory.display());
+}